@@ -14,7 +14,7 @@ fn main() {
             mask: Some(1234u32.to_be_bytes()),
             fin: true,
             reserved: 0,
-        });
+        }).unwrap();
         sansio_end.write_all(&header[..]).unwrap();
 
         frame_encoder.transform_frame_payload(&mut hello[..]);
@@ -6,7 +6,8 @@ use hyper_util::rt::TokioIo;
 use rand::Rng;
 use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::TcpStream};
 use websocket_sans_io::{
-    FrameInfo, Opcode, WebsocketFrameDecoder, WebsocketFrameEncoder, WebsocketFrameEvent,
+    generate_client_key, verify_accept_key, CloseCode, ClosePayload, FrameInfo, Opcode, Role,
+    ValidatingFrameDecoder, WebsocketFrameEncoder, WebsocketFrameEvent,
 };
 
 #[tokio::main(flavor = "current_thread")]
@@ -25,15 +26,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         }
     });
 
+    let key = generate_client_key(&rand::thread_rng().gen());
     let rq = hyper::Request::builder()
         .uri("/")
         .header("Connection", "Upgrade")
         .header("Upgrade", "websocket")
         .header("Sec-WebSocket-Version", "13")
-        .header("Sec-WebSocket-Key", "wmnj1sVQ7pHv1bVR/wraDw==")
+        .header("Sec-WebSocket-Key", &key[..])
         .body(Empty::new())?;
 
     let resp = sr.send_request(rq).await?;
+    let accept = resp.headers().get("Sec-WebSocket-Accept").map(|x| x.as_bytes());
+    if !accept.is_some_and(|accept| verify_accept_key(&key, accept)) {
+        return Err("Invalid Sec-WebSocket-Accept".into());
+    }
 
     let upg = hyper::upgrade::on(resp).await?;
     let Ok(parts) = upg.downcast::<TokioIo<tokio::net::TcpStream>>() else {
@@ -46,31 +52,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let mut buf = Vec::<u8>::with_capacity(debt.len().max(4096));
     buf.extend_from_slice(&debt[..]);
 
-    let mut frame_decoder = WebsocketFrameDecoder::new();
-    let mut frame_encoder = WebsocketFrameEncoder::new();
+    let mut frame_decoder = ValidatingFrameDecoder::with_role(Role::Client);
+    let mut frame_encoder = WebsocketFrameEncoder::with_role(Role::Client);
+    let mut masking_keys = || rand::thread_rng().gen();
     let mut bufptr = 0;
 
-    let mut error = false;
+    let mut close_code = CloseCode::Normal;
 
     println!("Connected to a WebSocket");
 
     loop {
         let bufslice = &mut buf[bufptr..];
-        let ret = frame_decoder.add_data(bufslice).unwrap();
+        let ret = match frame_decoder.add_data(bufslice) {
+            Ok(ret) => ret,
+            Err(e) => {
+                println!("Protocol violation: {e:?}");
+                close_code = e.close_code();
+                break;
+            }
+        };
         bufptr += ret.consumed_bytes;
         if let Some(ref ev) = ret.event {
             match ev {
                 WebsocketFrameEvent::Start{frame_info: mut fi, ..} => {
-                    if !fi.is_reasonable() {
-                        println!("Unreasonable frame");
-                        error = true;
-                        break;
-                    }
-                    if fi.mask.is_some() {
-                        println!("Masked frame while expected unmasked one");
-                        error = true;
-                        break;
-                    }
                     println!(
                         "Frame {:?} with payload length {}{}",
                         fi.opcode,
@@ -84,8 +88,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                         break;
                     }
 
-                    fi.mask = Some(rand::thread_rng().gen());
-                    let header = frame_encoder.start_frame(&fi);
+                    let header = frame_encoder.start_frame_with_key_source(&fi, &mut masking_keys);
                     s.write_all(&header[..]).await?;
                 }
                 WebsocketFrameEvent::PayloadChunk{original_opcode: _} => {
@@ -102,22 +105,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
             let n = s.read(&mut buf[..]).await?;
             if n == 0 {
                 println!("EOF");
-                error = true;
+                close_code = CloseCode::ProtocolError;
                 break;
             }
             buf.resize(n, 0);
         }
     }
 
-    let header = frame_encoder.start_frame(&FrameInfo {
+    let mut last_buf = ClosePayload::new(close_code).encode().unwrap();
+    let header = frame_encoder.start_frame_with_key_source(&FrameInfo {
         opcode: Opcode::ConnectionClose,
-        payload_length: 2,
-        mask: Some(rand::thread_rng().gen()),
+        payload_length: last_buf.len() as websocket_sans_io::PayloadLength,
+        mask: None,
         fin: true,
         reserved: 0,
-    });
+    }, &mut masking_keys);
     s.write_all(&header[..]).await?;
-    let mut last_buf : [u8; 2] = if error { 1002u16 } else { 1000u16 }.to_be_bytes();
     frame_encoder.transform_frame_payload(&mut last_buf[..]);
     s.write_all(&last_buf[..]).await?;
 
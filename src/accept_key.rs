@@ -0,0 +1,134 @@
+/// GUID appended to the client's key before hashing, as per RFC 6455.
+const WEBSOCKET_GUID: &[u8; 36] = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Get `idx`'th byte of SHA-1 padded message.
+const fn padded_byte(data: &[u8], idx: usize, padded_len: usize) -> u8 {
+    if idx < data.len() {
+        data[idx]
+    } else if idx == data.len() {
+        0x80
+    } else if idx >= padded_len - 8 {
+        let bit_len = (data.len() as u64) * 8;
+        (bit_len >> ((padded_len - 1 - idx) * 8)) as u8
+    } else {
+        0
+    }
+}
+
+/// Minimal SHA-1 implementation, just enough for `Sec-WebSocket-Accept` computation.
+pub(crate) const fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let padded_len = (data.len() + 9).div_ceil(64) * 64;
+    let mut block_start = 0;
+    while block_start < padded_len {
+        let mut w = [0u32; 80];
+        let mut i = 0;
+        while i < 16 {
+            let mut j = 0;
+            while j < 4 {
+                w[i] = (w[i] << 8) | padded_byte(data, block_start + i * 4 + j, padded_len) as u32;
+                j += 1;
+            }
+            i += 1;
+        }
+        while i < 80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+            i += 1;
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        i = 0;
+        while i < 80 {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(w[i]);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+            i += 1;
+        }
+        h[0] = h[0].wrapping_add(a);
+        h[1] = h[1].wrapping_add(b);
+        h[2] = h[2].wrapping_add(c);
+        h[3] = h[3].wrapping_add(d);
+        h[4] = h[4].wrapping_add(e);
+        block_start += 64;
+    }
+
+    let mut ret = [0u8; 20];
+    let mut i = 0;
+    while i < 20 {
+        ret[i] = (h[i / 4] >> (24 - (i % 4) * 8)) as u8;
+        i += 1;
+    }
+    ret
+}
+
+/// Base64-encode `N` bytes into `M` characters, with padding. `M` must be `(N + 2) / 3 * 4`.
+const fn base64_encode<const N: usize, const M: usize>(input: &[u8; N]) -> [u8; M] {
+    assert!(M == N.div_ceil(3) * 4);
+    let mut ret = [b'='; M];
+    let mut i = 0;
+    while i * 3 < N {
+        let b0 = input[i * 3] as u32;
+        let b1 = if i * 3 + 1 < N { input[i * 3 + 1] as u32 } else { 0 };
+        let b2 = if i * 3 + 2 < N { input[i * 3 + 2] as u32 } else { 0 };
+        let triple = (b0 << 16) | (b1 << 8) | b2;
+        ret[i * 4] = BASE64_ALPHABET[(triple >> 18) as usize & 0x3F];
+        ret[i * 4 + 1] = BASE64_ALPHABET[(triple >> 12) as usize & 0x3F];
+        if i * 3 + 1 < N {
+            ret[i * 4 + 2] = BASE64_ALPHABET[(triple >> 6) as usize & 0x3F];
+        }
+        if i * 3 + 2 < N {
+            ret[i * 4 + 3] = BASE64_ALPHABET[triple as usize & 0x3F];
+        }
+        i += 1;
+    }
+    ret
+}
+
+/// Compute `Sec-WebSocket-Accept` header value from `Sec-WebSocket-Key` header value.
+///
+/// ```
+/// use websocket_sans_io::compute_accept_key;
+/// assert_eq!(&compute_accept_key(b"dGhlIHNhbXBsZSBub25jZQ=="), b"s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+/// ```
+pub const fn compute_accept_key(key: &[u8; 24]) -> [u8; 28] {
+    let mut buf = [0u8; 24 + 36];
+    let mut i = 0;
+    while i < 24 {
+        buf[i] = key[i];
+        i += 1;
+    }
+    while i < 24 + 36 {
+        buf[i] = WEBSOCKET_GUID[i - 24];
+        i += 1;
+    }
+    base64_encode(&sha1(&buf))
+}
+
+/// Make a `Sec-WebSocket-Key` header value from 16 random bytes.
+///
+/// Bytes should come from a good random number generator.
+pub const fn generate_client_key(random_bytes: &[u8; 16]) -> [u8; 24] {
+    base64_encode(random_bytes)
+}
+
+/// Check that server's `Sec-WebSocket-Accept` header value corresponds to the key we have sent.
+pub fn verify_accept_key(key: &[u8; 24], accept: &[u8]) -> bool {
+    compute_accept_key(key)[..] == *accept
+}
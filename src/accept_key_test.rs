@@ -0,0 +1,48 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+const RFC_KEY: [u8; 24] = generate_client_key(b"the sample nonce");
+const RFC_ACCEPT: [u8; 28] = compute_accept_key(&RFC_KEY);
+
+#[test]
+fn accept_key_rfc_example() {
+    assert_eq!(&RFC_KEY, b"dGhlIHNhbXBsZSBub25jZQ==");
+    assert_eq!(&RFC_ACCEPT, b"s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    assert!(verify_accept_key(&RFC_KEY, b"s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+    assert!(!verify_accept_key(&RFC_KEY, b"s3pPLMBiTxaQ9kYGzzhZRbK+xOp="));
+    assert!(!verify_accept_key(&RFC_KEY, b""));
+}
+
+#[test]
+fn accept_key_sha1_vectors() {
+    fn hex(x: [u8; 20]) -> [u8; 40] {
+        let mut ret = [0; 40];
+        for (i, b) in x.iter().enumerate() {
+            ret[i * 2] = b"0123456789abcdef"[(b >> 4) as usize];
+            ret[i * 2 + 1] = b"0123456789abcdef"[(b & 0xF) as usize];
+        }
+        ret
+    }
+    assert_eq!(&hex(accept_key::sha1(b"")), b"da39a3ee5e6b4b0d3255bfef95601890afd80709");
+    assert_eq!(&hex(accept_key::sha1(b"abc")), b"a9993e364706816aba3e25717850c26c9cd0d89d");
+    assert_eq!(
+        &hex(accept_key::sha1(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+        b"84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+    );
+    assert_eq!(&hex(accept_key::sha1(&[b'a'; 1000])), b"291e9a6c66994949b57ba5e650361e98fc36b1ba");
+}
+
+#[test]
+fn accept_key_in_handshake() {
+    let request = b"GET /chat HTTP/1.1\r\nHost: x\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n";
+    let parsed = ParsedRequest::parse(request).unwrap().unwrap();
+    let mut buf = [0u8; 256];
+    let n = ServerResponse { accept: parsed.value.accept_key(), protocol: None, extensions: None, extra_headers: &[] }
+        .write(&mut buf)
+        .unwrap();
+    let response = ParsedResponse::parse(&buf[..n]).unwrap().unwrap();
+    assert_eq!(response.value.verify_key(&RFC_KEY), Ok(()));
+    assert_eq!(response.value.verify_key(b"AAAAAAAAAAAAAAAAAAAAAA=="), Err(HandshakeError::InvalidAccept));
+}
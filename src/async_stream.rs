@@ -0,0 +1,241 @@
+//! Runtime-independent part of asynchronous adapters.
+
+use core::task::{ready, Context, Poll};
+
+use alloc::vec::Vec;
+use std::io;
+
+use crate::connection::{Connection, ConnectionEvent};
+use crate::stream::{io_error, RandomMaskingKeys};
+use crate::{
+    CloseCode, ClosePayload, DecoderLimits, FrameInfo, MessageWriterError, Opcode, PayloadLength,
+    Role, WebsocketMessage, MAX_CONTROL_PAYLOAD_LENGTH,
+};
+
+/// Size of the buffer for incoming data.
+const READ_BUFFER_SIZE: usize = 4096;
+
+/// Non-blocking byte stream, abstracting over I/O traits of different async runtimes.
+pub(crate) trait AsyncTransport {
+    fn poll_read(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>>;
+    fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>>;
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+}
+
+/// Message-level WebSocket connection over an [`AsyncTransport`], implementing logic of `Stream` and `Sink`.
+#[derive(Debug)]
+pub(crate) struct AsyncWebSocket<T> {
+    pub(crate) inner: T,
+    connection: Connection<RandomMaskingKeys>,
+    buf: Vec<u8>,
+    start: usize,
+    end: usize,
+    message: Option<WebsocketMessage>,
+    read_closed: bool,
+    /// Encoded frames to be written to `inner`.
+    out: Vec<u8>,
+    out_offset: usize,
+    /// `out` contains only control frames queued by [`Connection`], not a message from `start_send`.
+    out_control_only: bool,
+}
+
+impl<T: AsyncTransport> AsyncWebSocket<T> {
+    pub(crate) fn new(inner: T, role: Role, data: &[u8]) -> Self {
+        let mut buf = Vec::with_capacity(READ_BUFFER_SIZE.max(data.len()));
+        buf.extend_from_slice(data);
+        let end = buf.len();
+        buf.resize(buf.capacity(), 0);
+        AsyncWebSocket {
+            inner,
+            connection: Connection::new(role, RandomMaskingKeys::new()),
+            buf,
+            start: 0,
+            end,
+            message: None,
+            read_closed: false,
+            out: Vec::new(),
+            out_offset: 0,
+            out_control_only: true,
+        }
+    }
+
+    /// Write out buffered frames. Queued control frames are picked up at frame boundaries.
+    fn poll_write_out(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            if self.out_offset == self.out.len() {
+                self.out.clear();
+                self.out_offset = 0;
+                let pending = self.connection.pending_output();
+                if pending.is_empty() {
+                    return Poll::Ready(Ok(()));
+                }
+                let n = pending.len();
+                self.out.extend_from_slice(pending);
+                self.connection.consume_pending_output(n);
+                self.out_control_only = true;
+            }
+            let n = ready!(self.inner.poll_write(cx, &self.out[self.out_offset..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.out_offset += n;
+        }
+    }
+
+    pub(crate) fn poll_next_message(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<io::Result<WebsocketMessage>>> {
+        loop {
+            // Pong or Close replies go out opportunistically; reading is not blocked on them.
+            // Messages being sent are left to the `Sink` side.
+            if self.out_control_only || self.out_offset == self.out.len() {
+                if let Poll::Ready(Err(e)) = self.poll_write_out(cx) {
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+            if self.read_closed {
+                return Poll::Ready(None);
+            }
+            let ret = match self
+                .connection
+                .add_data(&mut self.buf[self.start..self.end])
+            {
+                Ok(ret) => ret,
+                Err(e) => {
+                    self.read_closed = true;
+                    if self.out_control_only || self.out_offset == self.out.len() {
+                        let _ = self.poll_write_out(cx);
+                    }
+                    return Poll::Ready(Some(Err(io_error(io::ErrorKind::InvalidData, e))));
+                }
+            };
+            let payload = &self.buf[self.start..(self.start + ret.consumed_bytes)];
+            self.start += ret.consumed_bytes;
+            match ret.event {
+                Some(ConnectionEvent::MessageStart(opcode)) => {
+                    self.message = Some(WebsocketMessage {
+                        opcode,
+                        payload: Vec::new(),
+                    });
+                }
+                Some(ConnectionEvent::Payload) => {
+                    if let Some(ref mut message) = self.message {
+                        message.payload.extend_from_slice(payload);
+                    }
+                }
+                Some(ConnectionEvent::MessageEnd(_)) => {
+                    if let Some(message) = self.message.take() {
+                        return Poll::Ready(Some(Ok(message)));
+                    }
+                }
+                Some(ConnectionEvent::Closed) => self.read_closed = true,
+                None if ret.consumed_bytes > 0 => (),
+                None => {
+                    let n = ready!(self.inner.poll_read(cx, &mut self.buf))?;
+                    self.start = 0;
+                    self.end = n;
+                    if n == 0 {
+                        self.read_closed = true;
+                        if let Err(code) = self.connection.eof() {
+                            return Poll::Ready(Some(Err(io_error(
+                                io::ErrorKind::UnexpectedEof,
+                                code,
+                            ))));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Ready to accept a message when previous frames are written out.
+    pub(crate) fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_write_out(cx)
+    }
+
+    /// Encode a message as one frame. Should be called after [`AsyncWebSocket::poll_ready`] succeeds.
+    ///
+    /// Close frames go through the closing handshake like any other frame, so their payload is validated here.
+    pub(crate) fn start_send(&mut self, opcode: Opcode, payload: &[u8]) -> io::Result<()> {
+        if self.out_offset != self.out.len() {
+            return Err(io_error(
+                io::ErrorKind::InvalidInput,
+                MessageWriterError::MessageInProgress,
+            ));
+        }
+        match opcode {
+            Opcode::Text | Opcode::Binary => (),
+            Opcode::Ping | Opcode::Pong | Opcode::ConnectionClose => {
+                if payload.len() > MAX_CONTROL_PAYLOAD_LENGTH as usize {
+                    return Err(io_error(
+                        io::ErrorKind::InvalidInput,
+                        MessageWriterError::ControlPayloadTooLong,
+                    ));
+                }
+            }
+            _ => {
+                return Err(io_error(
+                    io::ErrorKind::InvalidInput,
+                    MessageWriterError::InvalidOpcode,
+                ))
+            }
+        }
+        if opcode == Opcode::ConnectionClose {
+            ClosePayload::parse(payload).map_err(|e| io_error(io::ErrorKind::InvalidInput, e))?;
+        }
+        self.out.clear();
+        self.out_offset = 0;
+        let pending = self.connection.pending_output();
+        let n = pending.len();
+        self.out.extend_from_slice(pending);
+        self.connection.consume_pending_output(n);
+        self.out_control_only = false;
+
+        let frame_info = FrameInfo {
+            opcode,
+            payload_length: PayloadLength::try_from(payload.len())
+                .map_err(|e| io_error(io::ErrorKind::InvalidInput, e))?,
+            mask: None,
+            fin: true,
+            reserved: 0,
+        };
+        let header = self
+            .connection
+            .start_frame(&frame_info)
+            .map_err(|e| io_error(io::ErrorKind::InvalidInput, e))?;
+        self.out.extend_from_slice(&header);
+        let offset = self.out.len();
+        self.out.extend_from_slice(payload);
+        self.connection
+            .transform_frame_payload(&mut self.out[offset..]);
+        Ok(())
+    }
+
+    pub(crate) fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_write_out(cx))?;
+        self.inner.poll_flush(cx)
+    }
+
+    /// Send a Close frame (unless already sent), flush and shut down the writing side.
+    pub(crate) fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_write_out(cx))?;
+        if !self.connection.close_sent() {
+            let payload = ClosePayload::new(CloseCode::Normal)
+                .encode()
+                .map_err(|e| io_error(io::ErrorKind::InvalidInput, e))?;
+            self.start_send(Opcode::ConnectionClose, &payload)?;
+        }
+        ready!(self.poll_flush(cx))?;
+        self.inner.poll_shutdown(cx)
+    }
+
+    pub(crate) fn set_limits(&mut self, limits: DecoderLimits) {
+        self.connection.set_limits(limits)
+    }
+
+    pub(crate) fn peer_close(&self) -> Option<ClosePayload<'_>> {
+        self.connection.peer_close()
+    }
+}
@@ -0,0 +1,217 @@
+use tinyvec::ArrayVec;
+
+use crate::MAX_CONTROL_PAYLOAD_LENGTH;
+
+/// Maximum length of the reason string in a [`crate::Opcode::ConnectionClose`] frame payload, in bytes.
+pub const MAX_CLOSE_REASON_LENGTH: usize = MAX_CONTROL_PAYLOAD_LENGTH as usize - 2;
+
+/// Status code of a [`crate::Opcode::ConnectionClose`] frame.
+///
+/// See [RFC 6455 section 7.4](https://www.rfc-editor.org/rfc/rfc6455#section-7.4) and
+/// [IANA registry](https://www.iana.org/assignments/websocket/websocket.xml#close-code-number).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CloseCode {
+    /// 1000, purpose for which the connection was established has been fulfilled.
+    Normal,
+    /// 1001, endpoint is going away, e.g. server shutdown or browser navigating away.
+    GoingAway,
+    /// 1002, protocol error.
+    ProtocolError,
+    /// 1003, endpoint received a type of data it cannot accept.
+    UnsupportedData,
+    /// 1005, no status code was present. Must not be sent on the wire.
+    NoStatusReceived,
+    /// 1006, connection was closed abnormally, without a close frame. Must not be sent on the wire.
+    Abnormal,
+    /// 1007, message content is inconsistent with its type, e.g. non-UTF-8 text message.
+    InvalidPayload,
+    /// 1008, message violates endpoint's policy.
+    PolicyViolation,
+    /// 1009, message is too big to process.
+    MessageTooBig,
+    /// 1010, client expected server to negotiate an extension.
+    MandatoryExtension,
+    /// 1011, server encountered an unexpected condition.
+    InternalError,
+    /// 1012, service is restarting.
+    ServiceRestart,
+    /// 1013, try again later.
+    TryAgainLater,
+    /// 1014, gateway or proxy received an invalid response from upstream.
+    BadGateway,
+    /// 1015, TLS handshake failure. Must not be sent on the wire.
+    TlsHandshake,
+    /// 3000-3999, codes registered with IANA for libraries, frameworks and applications.
+    Registered(u16),
+    /// 4000-4999, codes for private use.
+    Private(u16),
+    /// Codes that are reserved or invalid: 0-999, 1004, 1016-2999 and 5000 and above.
+    Reserved(u16),
+}
+
+impl CloseCode {
+    /// Interpret a numeric status code.
+    pub const fn from_u16(code: u16) -> CloseCode {
+        match code {
+            1000 => CloseCode::Normal,
+            1001 => CloseCode::GoingAway,
+            1002 => CloseCode::ProtocolError,
+            1003 => CloseCode::UnsupportedData,
+            1005 => CloseCode::NoStatusReceived,
+            1006 => CloseCode::Abnormal,
+            1007 => CloseCode::InvalidPayload,
+            1008 => CloseCode::PolicyViolation,
+            1009 => CloseCode::MessageTooBig,
+            1010 => CloseCode::MandatoryExtension,
+            1011 => CloseCode::InternalError,
+            1012 => CloseCode::ServiceRestart,
+            1013 => CloseCode::TryAgainLater,
+            1014 => CloseCode::BadGateway,
+            1015 => CloseCode::TlsHandshake,
+            3000..=3999 => CloseCode::Registered(code),
+            4000..=4999 => CloseCode::Private(code),
+            _ => CloseCode::Reserved(code),
+        }
+    }
+
+    /// Get numeric value of this status code.
+    pub const fn as_u16(&self) -> u16 {
+        match self {
+            CloseCode::Normal => 1000,
+            CloseCode::GoingAway => 1001,
+            CloseCode::ProtocolError => 1002,
+            CloseCode::UnsupportedData => 1003,
+            CloseCode::NoStatusReceived => 1005,
+            CloseCode::Abnormal => 1006,
+            CloseCode::InvalidPayload => 1007,
+            CloseCode::PolicyViolation => 1008,
+            CloseCode::MessageTooBig => 1009,
+            CloseCode::MandatoryExtension => 1010,
+            CloseCode::InternalError => 1011,
+            CloseCode::ServiceRestart => 1012,
+            CloseCode::TryAgainLater => 1013,
+            CloseCode::BadGateway => 1014,
+            CloseCode::TlsHandshake => 1015,
+            CloseCode::Registered(x) => *x,
+            CloseCode::Private(x) => *x,
+            CloseCode::Reserved(x) => *x,
+        }
+    }
+
+    /// Check if this code may appear in a close frame payload.
+    ///
+    /// `false` for 1005, 1006 and 1015 (which are meant only for reporting to applications),
+    /// for [`CloseCode::Reserved`] codes and for [`CloseCode::Registered`] or [`CloseCode::Private`]
+    /// constructed with a number outside of their range. Receiving such code should be treated as a protocol error.
+    pub const fn is_allowed_on_wire(&self) -> bool {
+        match self {
+            CloseCode::NoStatusReceived
+            | CloseCode::Abnormal
+            | CloseCode::TlsHandshake
+            | CloseCode::Reserved(_) => false,
+            CloseCode::Registered(x) => matches!(x, 3000..=3999),
+            CloseCode::Private(x) => matches!(x, 4000..=4999),
+            _ => true,
+        }
+    }
+}
+
+impl From<u16> for CloseCode {
+    fn from(value: u16) -> Self {
+        CloseCode::from_u16(value)
+    }
+}
+
+impl From<CloseCode> for u16 {
+    fn from(value: CloseCode) -> Self {
+        value.as_u16()
+    }
+}
+
+/// Problem with content of a [`crate::Opcode::ConnectionClose`] frame payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ClosePayloadError {
+    /// Payload is one byte long, which is not enough for a status code.
+    Truncated,
+    /// Payload is longer than 125 bytes.
+    TooLong,
+    /// Status code is not allowed to be sent on the wire, see [`CloseCode::is_allowed_on_wire`].
+    InvalidCode(CloseCode),
+    /// Reason is not a valid UTF-8.
+    InvalidUtf8,
+    /// Reason is longer than [`MAX_CLOSE_REASON_LENGTH`].
+    ReasonTooLong,
+    /// Non-empty reason is specified without a status code.
+    ReasonWithoutCode,
+}
+
+impl ClosePayloadError {
+    /// Status code to be sent in reply when receiving close frame with such problem.
+    pub const fn close_code(&self) -> CloseCode {
+        match self {
+            ClosePayloadError::InvalidUtf8 => CloseCode::InvalidPayload,
+            _ => CloseCode::ProtocolError,
+        }
+    }
+}
+
+/// Content of a [`crate::Opcode::ConnectionClose`] frame: optional status code and a reason string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ClosePayload<'a> {
+    /// Status code. `None` means the payload is empty.
+    pub code: Option<CloseCode>,
+    /// Human-readable reason. Should be empty if `code` is `None`.
+    pub reason: &'a str,
+}
+
+impl<'a> ClosePayload<'a> {
+    /// Create close payload with the given code and empty reason.
+    pub const fn new(code: CloseCode) -> ClosePayload<'static> {
+        ClosePayload {
+            code: Some(code),
+            reason: "",
+        }
+    }
+
+    /// Parse (unmasked) payload of a received close frame.
+    pub fn parse(payload: &'a [u8]) -> Result<ClosePayload<'a>, ClosePayloadError> {
+        match payload.len() {
+            0 => return Ok(ClosePayload::default()),
+            1 => return Err(ClosePayloadError::Truncated),
+            x if x > MAX_CONTROL_PAYLOAD_LENGTH as usize => return Err(ClosePayloadError::TooLong),
+            _ => (),
+        }
+        let code = CloseCode::from_u16(u16::from_be_bytes([payload[0], payload[1]]));
+        if !code.is_allowed_on_wire() {
+            return Err(ClosePayloadError::InvalidCode(code));
+        }
+        let reason =
+            core::str::from_utf8(&payload[2..]).map_err(|_| ClosePayloadError::InvalidUtf8)?;
+        Ok(ClosePayload {
+            code: Some(code),
+            reason,
+        })
+    }
+
+    /// Serialize close frame payload, checking that it can be sent on the wire.
+    ///
+    /// Returned bytes are not masked, use [`crate::WebsocketFrameEncoder::transform_frame_payload`] for that.
+    pub fn encode(&self) -> Result<ArrayVec<[u8; MAX_CONTROL_PAYLOAD_LENGTH as usize]>, ClosePayloadError> {
+        let mut ret = ArrayVec::new();
+        let Some(code) = self.code else {
+            if !self.reason.is_empty() {
+                return Err(ClosePayloadError::ReasonWithoutCode);
+            }
+            return Ok(ret);
+        };
+        if !code.is_allowed_on_wire() {
+            return Err(ClosePayloadError::InvalidCode(code));
+        }
+        if self.reason.len() > MAX_CLOSE_REASON_LENGTH {
+            return Err(ClosePayloadError::ReasonTooLong);
+        }
+        ret.extend(code.as_u16().to_be_bytes());
+        ret.extend_from_slice(self.reason.as_bytes());
+        Ok(ret)
+    }
+}
@@ -0,0 +1,50 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+#[test]
+fn close_code_roundtrip() {
+    for code in 0..=u16::MAX {
+        assert_eq!(CloseCode::from_u16(code).as_u16(), code);
+    }
+}
+
+#[test]
+fn close_code_wire_validity() {
+    assert!(CloseCode::Normal.is_allowed_on_wire());
+    assert!(CloseCode::from_u16(1014).is_allowed_on_wire());
+    assert!(CloseCode::from_u16(3000).is_allowed_on_wire());
+    assert!(CloseCode::from_u16(4999).is_allowed_on_wire());
+    for code in [0, 999, 1004, 1005, 1006, 1015, 1016, 2999, 5000, 65535] {
+        assert!(!CloseCode::from_u16(code).is_allowed_on_wire(), "{code}");
+    }
+    for code in [CloseCode::Private(5), CloseCode::Private(3000), CloseCode::Registered(1000), CloseCode::Registered(4000)] {
+        assert!(!code.is_allowed_on_wire(), "{code:?}");
+        assert_eq!(ClosePayload::new(code).encode(), Err(ClosePayloadError::InvalidCode(code)));
+    }
+}
+
+#[test]
+fn close_payload_parse() {
+    assert_eq!(ClosePayload::parse(b""), Ok(ClosePayload { code: None, reason: "" }));
+    assert_eq!(ClosePayload::parse(b"\x03"), Err(ClosePayloadError::Truncated));
+    assert_eq!(ClosePayload::parse(b"\x03\xE8"), Ok(ClosePayload::new(CloseCode::Normal)));
+    assert_eq!(ClosePayload::parse(b"\x0F\xA0bye"), Ok(ClosePayload { code: Some(CloseCode::Private(4000)), reason: "bye" }));
+    assert_eq!(ClosePayload::parse(b"\x03\xED"), Err(ClosePayloadError::InvalidCode(CloseCode::NoStatusReceived)));
+    assert_eq!(ClosePayload::parse(b"\x03\xE8\xFF"), Err(ClosePayloadError::InvalidUtf8));
+    assert_eq!(ClosePayload::parse(b"\x03\xE8\xFF").unwrap_err().close_code(), CloseCode::InvalidPayload);
+}
+
+#[test]
+fn close_payload_encode() {
+    assert_eq!(&ClosePayload::new(CloseCode::GoingAway).encode().unwrap()[..], b"\x03\xE9");
+    assert_eq!(&ClosePayload { code: Some(CloseCode::Normal), reason: "ok" }.encode().unwrap()[..], b"\x03\xE8ok");
+    assert_eq!(&ClosePayload::default().encode().unwrap()[..], b"");
+    assert_eq!(ClosePayload { code: None, reason: "x" }.encode(), Err(ClosePayloadError::ReasonWithoutCode));
+    assert_eq!(ClosePayload::new(CloseCode::Abnormal).encode(), Err(ClosePayloadError::InvalidCode(CloseCode::Abnormal)));
+
+    let long = [b'x'; MAX_CLOSE_REASON_LENGTH + 1];
+    let long = core::str::from_utf8(&long).unwrap();
+    assert_eq!(ClosePayload { code: Some(CloseCode::Normal), reason: &long[..MAX_CLOSE_REASON_LENGTH] }.encode().unwrap().len(), 125);
+    assert_eq!(ClosePayload { code: Some(CloseCode::Normal), reason: long }.encode(), Err(ClosePayloadError::ReasonTooLong));
+}
@@ -0,0 +1,163 @@
+use crate::{CloseCode, FrameInfo, Opcode, WebsocketFrameEvent};
+
+/// State of the WebSocket closing handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum CloseState {
+    /// No close frames were sent or received.
+    #[default]
+    Open,
+    /// We have sent a close frame and wait for the peer to reply with a close frame.
+    CloseSent,
+    /// Peer has sent a close frame. We should reply with a close frame as soon as possible.
+    /// Data frames can still be sent until then, e.g. to finish a fragmented message.
+    CloseReceived,
+    /// Close frames were both sent and received. TCP connection can be shut down.
+    Closed,
+}
+
+/// What the caller should do in response to an incoming frame event, as reported by [`CloseHandshake::incoming_event`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CloseHandshakeAction {
+    /// Handle the event as usual.
+    Continue,
+    /// Peer initiated the closing handshake. Send a close frame in reply (typically echoing the status code).
+    /// RFC 6455 allows finishing the message being sent first; no data frames can be sent after the reply.
+    ReplyWithClose,
+    /// Closing handshake is complete, it is now safe to shut down the TCP connection.
+    Shutdown,
+    /// A frame arrived after peer's close frame. Such frame must not be processed, the connection should be failed.
+    Reject,
+}
+
+/// Error returned by [`CloseHandshake::outgoing_frame`] when the frame must not be sent in current state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CloseHandshakeError {
+    /// No data frames can be sent after a close frame.
+    DataAfterClose,
+    /// Close frame was already sent.
+    DuplicateClose,
+}
+
+/// Sans-IO tracker of the WebSocket closing handshake.
+///
+/// Feed it events from [`crate::WebsocketFrameDecoder`] using [`CloseHandshake::incoming_event`] and headers of frames
+/// being sent with [`crate::WebsocketFrameEncoder`] using [`CloseHandshake::outgoing_frame`]. It tells
+/// when to reply with a close frame, which frames must be rejected and when the TCP connection can be shut down.
+///
+/// Content of close frames (see [`crate::ClosePayload`]) is not inspected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CloseHandshake {
+    state: CloseState,
+}
+
+impl CloseHandshake {
+    /// Create new instance in [`CloseState::Open`] state.
+    #[inline]
+    pub const fn new() -> Self {
+        CloseHandshake {
+            state: CloseState::Open,
+        }
+    }
+
+    /// Current state.
+    #[inline]
+    pub const fn state(&self) -> CloseState {
+        self.state
+    }
+
+    /// Observe an incoming frame event.
+    ///
+    /// Close frame is considered received at its [`WebsocketFrameEvent::End`], after its payload is delivered.
+    pub fn incoming_event(&mut self, event: &WebsocketFrameEvent) -> CloseHandshakeAction {
+        match event {
+            WebsocketFrameEvent::Start { .. } | WebsocketFrameEvent::PayloadChunk { .. } => {
+                if self.close_received() {
+                    CloseHandshakeAction::Reject
+                } else {
+                    CloseHandshakeAction::Continue
+                }
+            }
+            WebsocketFrameEvent::End { frame_info, .. } => {
+                if self.close_received() {
+                    return CloseHandshakeAction::Reject;
+                }
+                if frame_info.opcode != Opcode::ConnectionClose {
+                    return CloseHandshakeAction::Continue;
+                }
+                match self.state {
+                    CloseState::Open => {
+                        self.state = CloseState::CloseReceived;
+                        CloseHandshakeAction::ReplyWithClose
+                    }
+                    _ => {
+                        self.state = CloseState::Closed;
+                        CloseHandshakeAction::Shutdown
+                    }
+                }
+            }
+        }
+    }
+
+    /// Register a frame which is about to be sent.
+    ///
+    /// Returns [`CloseHandshakeAction::Shutdown`] when this frame is a reply to peer's close frame,
+    /// after which TCP connection can be shut down. Otherwise returns [`CloseHandshakeAction::Continue`].
+    ///
+    /// Data frames are rejected only after our close frame has been sent. They are still allowed in
+    /// [`CloseState::CloseReceived`] state, so that a message in progress can be completed before replying.
+    pub fn outgoing_frame(
+        &mut self,
+        frame_info: &FrameInfo,
+    ) -> Result<CloseHandshakeAction, CloseHandshakeError> {
+        let closing = matches!(self.state, CloseState::CloseSent | CloseState::Closed);
+        if frame_info.opcode == Opcode::ConnectionClose {
+            if closing {
+                return Err(CloseHandshakeError::DuplicateClose);
+            }
+            if self.state == CloseState::CloseReceived {
+                self.state = CloseState::Closed;
+                return Ok(CloseHandshakeAction::Shutdown);
+            }
+            self.state = CloseState::CloseSent;
+        } else if frame_info.opcode.is_data() && closing {
+            return Err(CloseHandshakeError::DataAfterClose);
+        }
+        Ok(CloseHandshakeAction::Continue)
+    }
+
+    /// Whether a close frame was received from the peer.
+    #[inline]
+    pub const fn close_received(&self) -> bool {
+        matches!(self.state, CloseState::CloseReceived | CloseState::Closed)
+    }
+
+    /// Whether a close frame was sent to the peer.
+    #[inline]
+    pub const fn close_sent(&self) -> bool {
+        matches!(self.state, CloseState::CloseSent | CloseState::Closed)
+    }
+
+    /// Whether TCP connection can be shut down now.
+    ///
+    /// It is recommended for clients to wait for the server to close TCP connection first.
+    #[inline]
+    pub const fn can_shutdown(&self) -> bool {
+        matches!(self.state, CloseState::Closed)
+    }
+
+    /// Handle end of incoming byte stream.
+    ///
+    /// `eof_valid` should come from [`crate::WebsocketFrameDecoder::eof_valid`].
+    ///
+    /// Returns `Ok` if connection was closed cleanly, after peer's close frame.
+    /// Otherwise returns [`CloseCode::Abnormal`] (1006), which should be reported to the application.
+    pub fn eof(&mut self, eof_valid: bool) -> Result<(), CloseCode> {
+        let clean = eof_valid && self.close_received();
+        self.state = CloseState::Closed;
+        if clean {
+            Ok(())
+        } else {
+            Err(CloseCode::Abnormal)
+        }
+    }
+}
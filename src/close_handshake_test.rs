@@ -0,0 +1,59 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+fn frame(opcode: Opcode) -> FrameInfo {
+    FrameInfo { opcode, payload_length: 0, mask: None, fin: true, reserved: 0 }
+}
+
+fn start(opcode: Opcode) -> WebsocketFrameEvent {
+    WebsocketFrameEvent::Start { frame_info: frame(opcode), original_opcode: opcode }
+}
+
+fn end(opcode: Opcode) -> WebsocketFrameEvent {
+    WebsocketFrameEvent::End { frame_info: frame(opcode), original_opcode: opcode }
+}
+
+#[test]
+fn close_initiated_by_peer() {
+    let mut h = CloseHandshake::new();
+    assert_eq!(h.incoming_event(&start(Opcode::Text)), CloseHandshakeAction::Continue);
+    assert_eq!(h.incoming_event(&end(Opcode::Text)), CloseHandshakeAction::Continue);
+    assert_eq!(h.incoming_event(&start(Opcode::ConnectionClose)), CloseHandshakeAction::Continue);
+    assert_eq!(h.incoming_event(&end(Opcode::ConnectionClose)), CloseHandshakeAction::ReplyWithClose);
+    assert_eq!(h.state(), CloseState::CloseReceived);
+    assert_eq!(h.incoming_event(&start(Opcode::Binary)), CloseHandshakeAction::Reject);
+    // We may still finish our current message before replying.
+    assert_eq!(h.outgoing_frame(&frame(Opcode::Text)), Ok(CloseHandshakeAction::Continue));
+    assert_eq!(h.outgoing_frame(&frame(Opcode::ConnectionClose)), Ok(CloseHandshakeAction::Shutdown));
+    assert_eq!(h.outgoing_frame(&frame(Opcode::Text)), Err(CloseHandshakeError::DataAfterClose));
+    assert!(h.can_shutdown());
+    assert_eq!(h.eof(true), Ok(()));
+}
+
+#[test]
+fn close_initiated_by_us() {
+    let mut h = CloseHandshake::new();
+    assert_eq!(h.outgoing_frame(&frame(Opcode::ConnectionClose)), Ok(CloseHandshakeAction::Continue));
+    assert_eq!(h.state(), CloseState::CloseSent);
+    assert_eq!(h.outgoing_frame(&frame(Opcode::Binary)), Err(CloseHandshakeError::DataAfterClose));
+    assert_eq!(h.outgoing_frame(&frame(Opcode::ConnectionClose)), Err(CloseHandshakeError::DuplicateClose));
+    assert_eq!(h.outgoing_frame(&frame(Opcode::Pong)), Ok(CloseHandshakeAction::Continue));
+    // Peer may still send data before replying
+    assert_eq!(h.incoming_event(&end(Opcode::Binary)), CloseHandshakeAction::Continue);
+    assert_eq!(h.incoming_event(&end(Opcode::ConnectionClose)), CloseHandshakeAction::Shutdown);
+    assert_eq!(h.state(), CloseState::Closed);
+}
+
+#[test]
+fn close_abnormal_eof() {
+    assert_eq!(CloseHandshake::new().eof(true), Err(CloseCode::Abnormal));
+
+    let mut h = CloseHandshake::new();
+    h.outgoing_frame(&frame(Opcode::ConnectionClose)).unwrap();
+    assert_eq!(h.eof(true), Err(CloseCode::Abnormal));
+
+    let mut h = CloseHandshake::new();
+    h.incoming_event(&end(Opcode::ConnectionClose));
+    assert_eq!(h.eof(false), Err(CloseCode::Abnormal));
+}
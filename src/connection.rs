@@ -0,0 +1,282 @@
+//! Protocol logic shared by I/O adapters: message events, automatic Pong replies and closing handshake.
+
+use tinyvec::ArrayVec;
+
+use crate::{
+    encode_frame_header, masking, CloseCode, CloseHandshake, CloseHandshakeAction,
+    CloseHandshakeError, ClosePayload, DecoderLimits, FrameInfo, MaskingKeySource, Opcode,
+    PayloadLength, PongResponder, ProtocolViolation, Role, ValidatingFrameDecoder,
+    WebsocketFrameEncoder, WebsocketFrameEvent, MAX_CONTROL_PAYLOAD_LENGTH, MAX_HEADER_LENGTH,
+};
+
+/// Maximum size of an encoded control frame: two basic header bytes, masking key and the payload.
+const MAX_CONTROL_FRAME_LENGTH: usize = 2 + 4 + MAX_CONTROL_PAYLOAD_LENGTH as usize;
+
+/// Capacity of [`Connection::pending_output`]. It holds at most a partially sent frame,
+/// one not yet sent Pong (newer Pings replace it) and a Close frame.
+const PENDING_OUTPUT_CAPACITY: usize = 3 * MAX_CONTROL_FRAME_LENGTH;
+
+/// Event reported by [`Connection::add_data`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConnectionEvent {
+    /// A data message with given opcode is started.
+    MessageStart(Opcode),
+    /// Consumed bytes are (unmasked) payload of current data message.
+    Payload,
+    /// Current data message is finished.
+    MessageEnd(Opcode),
+    /// Closing handshake is complete, no more data should be read.
+    Closed,
+}
+
+/// Return value of [`Connection::add_data`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ConnectionAddDataResult {
+    pub(crate) consumed_bytes: usize,
+    pub(crate) event: Option<ConnectionEvent>,
+}
+
+/// Sans-IO core of a WebSocket connection.
+///
+/// Incoming data is validated and control frames are handled internally: replies to Pings and Close frames
+/// are queued and must be sent (see [`Connection::pending_output`]) before the next outgoing frame.
+/// While the output is not being sent, only the Pong for the most recent Ping is kept, as permitted by RFC 6455.
+///
+/// Peer's Close frame is echoed back as soon as it arrives. Although RFC 6455 allows to finish sending
+/// a fragmented message before that (see [`CloseHandshakeAction::ReplyWithClose`]), further frames of such message
+/// are rejected by [`Connection::start_frame`] with [`CloseHandshakeError::DataAfterClose`].
+#[derive(Debug, Clone)]
+pub(crate) struct Connection<K> {
+    decoder: ValidatingFrameDecoder,
+    encoder: WebsocketFrameEncoder,
+    key_source: K,
+    pong: PongResponder,
+    close: CloseHandshake,
+    incoming_control: Option<Opcode>,
+    close_payload: ArrayVec<[u8; MAX_CONTROL_PAYLOAD_LENGTH as usize]>,
+    pending_output: ArrayVec<[u8; PENDING_OUTPUT_CAPACITY]>,
+    /// Offset of a Pong frame within `pending_output` which was not started to be sent, so it can be replaced.
+    unsent_pong: Option<usize>,
+}
+
+impl<K: MaskingKeySource> Connection<K> {
+    pub(crate) fn new(role: Role, key_source: K) -> Self {
+        Connection {
+            decoder: ValidatingFrameDecoder::with_role(role),
+            encoder: WebsocketFrameEncoder::with_role(role),
+            key_source,
+            pong: PongResponder::new(),
+            close: CloseHandshake::new(),
+            incoming_control: None,
+            close_payload: ArrayVec::new(),
+            pending_output: ArrayVec::new(),
+            unsent_pong: None,
+        }
+    }
+
+    /// Handle incoming bytes. Should be called again when nonzero bytes were consumed or an event was returned.
+    ///
+    /// On error, a Close frame with appropriate status code is queued.
+    pub(crate) fn add_data(
+        &mut self,
+        data: &mut [u8],
+    ) -> Result<ConnectionAddDataResult, ProtocolViolation> {
+        loop {
+            let ret = match self.decoder.add_data(data) {
+                Ok(ret) => ret,
+                Err(e) => {
+                    self.queue_close(&ClosePayload::new(e.close_code()));
+                    return Err(e);
+                }
+            };
+            let Some(ev) = ret.event else {
+                return Ok(ConnectionAddDataResult {
+                    consumed_bytes: ret.consumed_bytes,
+                    event: None,
+                });
+            };
+            let event = self.handle_frame_event(ev, &data[..ret.consumed_bytes]);
+            if event.is_some() || ret.consumed_bytes > 0 {
+                return Ok(ConnectionAddDataResult {
+                    consumed_bytes: ret.consumed_bytes,
+                    event,
+                });
+            }
+        }
+    }
+
+    fn handle_frame_event(
+        &mut self,
+        ev: WebsocketFrameEvent,
+        payload: &[u8],
+    ) -> Option<ConnectionEvent> {
+        let action = self.close.incoming_event(&ev);
+        if action == CloseHandshakeAction::Reject {
+            return None;
+        }
+        self.pong.handle_event(&ev, payload);
+        if self.pong.pong_pending() && !self.close.close_sent() {
+            let mask = self.next_mask();
+            if let Some(pong) = self.pong.take_pong(mask) {
+                self.queue_pong(pong.as_bytes());
+            }
+        }
+        match ev {
+            WebsocketFrameEvent::Start { frame_info, .. } if frame_info.opcode.is_control() => {
+                self.incoming_control = Some(frame_info.opcode);
+                self.close_payload.clear();
+                None
+            }
+            WebsocketFrameEvent::Start { frame_info, .. }
+                if frame_info.opcode != Opcode::Continuation =>
+            {
+                Some(ConnectionEvent::MessageStart(frame_info.opcode))
+            }
+            WebsocketFrameEvent::PayloadChunk { .. } if self.incoming_control.is_some() => {
+                if self.incoming_control == Some(Opcode::ConnectionClose) {
+                    let n = payload
+                        .len()
+                        .min(self.close_payload.capacity() - self.close_payload.len());
+                    self.close_payload.extend_from_slice(&payload[..n]);
+                }
+                None
+            }
+            WebsocketFrameEvent::PayloadChunk { .. } => Some(ConnectionEvent::Payload),
+            WebsocketFrameEvent::End { frame_info, .. } if frame_info.opcode.is_control() => {
+                self.incoming_control = None;
+                match action {
+                    CloseHandshakeAction::ReplyWithClose => {
+                        let code = match ClosePayload::parse(&self.close_payload) {
+                            Ok(ClosePayload {
+                                code: Some(code), ..
+                            }) => code,
+                            Ok(ClosePayload { code: None, .. }) => CloseCode::Normal,
+                            Err(e) => e.close_code(),
+                        };
+                        self.queue_close(&ClosePayload::new(code));
+                        Some(ConnectionEvent::Closed)
+                    }
+                    CloseHandshakeAction::Shutdown => Some(ConnectionEvent::Closed),
+                    _ => None,
+                }
+            }
+            WebsocketFrameEvent::End {
+                frame_info,
+                original_opcode,
+            } if frame_info.fin => Some(ConnectionEvent::MessageEnd(original_opcode)),
+            _ => None,
+        }
+    }
+
+    /// Masking key for a frame not going through the encoder.
+    fn next_mask(&mut self) -> Option<[u8; 4]> {
+        match self.encoder.role() {
+            Some(role) if role.masks_outgoing() => Some(self.key_source.next_masking_key()),
+            _ => None,
+        }
+    }
+
+    /// Queue a Close frame, unless one was already sent.
+    fn queue_close(&mut self, payload: &ClosePayload<'_>) {
+        let fi = FrameInfo {
+            opcode: Opcode::ConnectionClose,
+            payload_length: 0,
+            mask: None,
+            fin: true,
+            reserved: 0,
+        };
+        if self.close.outgoing_frame(&fi).is_err() {
+            return;
+        }
+        let Ok(mut payload) = payload.encode() else {
+            return;
+        };
+        let mask = self.next_mask();
+        let mut frame: ArrayVec<[u8; MAX_CONTROL_FRAME_LENGTH]> = ArrayVec::new();
+        frame.extend_from_slice(&encode_frame_header(&FrameInfo {
+            payload_length: payload.len() as PayloadLength,
+            mask,
+            ..fi
+        }));
+        if let Some(mask) = mask {
+            masking::apply_mask(mask, &mut payload, 0);
+        }
+        frame.extend_from_slice(&payload);
+        self.queue_output(&frame);
+    }
+
+    /// Queue a Pong frame, replacing the previous one if it was not started to be sent.
+    fn queue_pong(&mut self, frame: &[u8]) {
+        // Nothing is queued after a Pong, as Pongs stop after a Close frame is queued.
+        if let Some(offset) = self.unsent_pong.take() {
+            self.pending_output.truncate(offset);
+        }
+        self.unsent_pong = Some(self.pending_output.len());
+        self.queue_output(frame);
+    }
+
+    /// Queue a whole frame. Frames are never truncated or dropped: [`PENDING_OUTPUT_CAPACITY`] accounts for all that can be queued.
+    fn queue_output(&mut self, frame: &[u8]) {
+        assert!(
+            frame.len() <= self.pending_output.capacity() - self.pending_output.len(),
+            "pending output overflow"
+        );
+        self.pending_output.extend_from_slice(frame);
+    }
+
+    /// Control frames which should be sent before anything else.
+    #[inline]
+    pub(crate) fn pending_output(&self) -> &[u8] {
+        &self.pending_output
+    }
+
+    /// Mark first `n` bytes of [`Connection::pending_output`] as sent.
+    pub(crate) fn consume_pending_output(&mut self, n: usize) {
+        self.pending_output.drain(..n);
+        self.unsent_pong = match self.unsent_pong {
+            Some(offset) if offset >= n => Some(offset - n),
+            _ => None,
+        };
+    }
+
+    /// Begin an outgoing frame, returning its header. Payload should be passed through [`Connection::transform_frame_payload`].
+    pub(crate) fn start_frame(
+        &mut self,
+        frame_info: &FrameInfo,
+    ) -> Result<ArrayVec<[u8; MAX_HEADER_LENGTH]>, CloseHandshakeError> {
+        self.close.outgoing_frame(frame_info)?;
+        Ok(self
+            .encoder
+            .start_frame_with_key_source(frame_info, &mut self.key_source))
+    }
+
+    #[inline]
+    pub(crate) fn transform_frame_payload(&mut self, data: &mut [u8]) {
+        self.encoder.transform_frame_payload(data)
+    }
+
+    /// Whether a Close frame was sent or queued.
+    #[inline]
+    #[cfg(any(feature = "tokio", feature = "futures_io"))]
+    pub(crate) fn close_sent(&self) -> bool {
+        self.close.close_sent()
+    }
+
+    /// Set resource limits for incoming frames. Exceeding them queues a Close frame with [`CloseCode::MessageTooBig`].
+    pub(crate) fn set_limits(&mut self, limits: DecoderLimits) {
+        self.decoder.set_limits(limits)
+    }
+
+    /// Payload of peer's Close frame, if it was received.
+    pub(crate) fn peer_close(&self) -> Option<ClosePayload<'_>> {
+        if !self.close.close_received() {
+            return None;
+        }
+        ClosePayload::parse(&self.close_payload).ok()
+    }
+
+    /// Handle end of incoming byte stream, returning [`CloseCode::Abnormal`] if it was not clean.
+    pub(crate) fn eof(&mut self) -> Result<(), CloseCode> {
+        self.close.eof(self.decoder.eof_valid())
+    }
+}
@@ -0,0 +1,58 @@
+use super::*;
+
+extern crate std;
+
+use std::vec::Vec;
+
+use pretty_assertions::assert_eq;
+
+use crate::connection::{Connection, ConnectionEvent};
+use crate::test_util::{close_payload, decode_events, frame, frames};
+
+/// Feed all of `data` to the connection, collecting events.
+fn feed(connection: &mut Connection<impl MaskingKeySource>, data: Vec<u8>) -> Vec<ConnectionEvent> {
+    let events = decode_events(connection, &data, usize::MAX).unwrap();
+    events.into_iter().map(|(event, _)| event).collect()
+}
+
+#[test]
+fn connection_replaces_unsent_pong() {
+    let mut c = Connection::new(Role::Client, || [1, 2, 3, 4]);
+    feed(&mut c, frame(Opcode::Ping, true, None, &[b'x'; 125]));
+    // The first Pong is partially sent, so it must be kept whole.
+    let mut sent = c.pending_output()[..3].to_vec();
+    c.consume_pending_output(3);
+
+    // Largest possible frames: only the most recent Pong is kept, followed by the Close reply.
+    let input = [
+        frame(Opcode::Ping, true, None, &[b'a'; 125]),
+        frame(Opcode::Ping, true, None, &[b'b'; 125]),
+        frame(
+            Opcode::ConnectionClose,
+            true,
+            None,
+            &close_payload(CloseCode::GoingAway),
+        ),
+        frame(Opcode::Ping, true, None, &[b'c'; 125]),
+    ];
+    assert_eq!(feed(&mut c, input[..3].concat()), [ConnectionEvent::Closed]);
+    // Nothing is processed or replied to after the closing handshake.
+    assert_eq!(feed(&mut c, input[3].clone()), []);
+
+    sent.extend_from_slice(c.pending_output());
+    c.consume_pending_output(c.pending_output().len());
+    assert_eq!(
+        frames(sent),
+        std::vec![
+            (Opcode::Pong, true, true, std::vec![b'x'; 125]),
+            (Opcode::Pong, true, true, std::vec![b'b'; 125]),
+            (
+                Opcode::ConnectionClose,
+                true,
+                true,
+                close_payload(CloseCode::GoingAway)
+            ),
+        ]
+    );
+    assert!(c.pending_output().is_empty());
+}
@@ -323,3 +323,270 @@ fn decode_bin64k_masked_chunks2() {
         WebsocketFrameEvent::End{original_opcode: Opcode::Binary, frame_info: FrameInfo { opcode: Opcode::Binary, payload_length: 65536, mask: Some(*b"\x11\x22\x33\x44"), fin: true, reserved: 0 }},
     ]));
 }
+
+fn decode_copy(input: &[u8], max_chunk_size: usize, output_size: usize) -> (Vec<u8>, Vec<WebsocketFrameEvent>) {
+    let mut payload = Vec::new();
+    let mut events = Vec::new();
+    let mut d = frame_decoding::WebsocketFrameDecoder::new();
+    let mut obuf = vec![0u8; output_size];
+    for mut chunk in input.chunks(max_chunk_size).chain([&[][..]]) {
+        loop {
+            let ret = d.add_data_copy(chunk, &mut obuf).unwrap();
+            payload.extend_from_slice(&obuf[..ret.produced_bytes]);
+            chunk = &chunk[ret.consumed_bytes..];
+            match ret.event {
+                None if ret.consumed_bytes == 0 => break,
+                None => (),
+                Some(WebsocketFrameEvent::PayloadChunk { .. }) => (),
+                Some(ev) => events.push(ev),
+            }
+        }
+    }
+    (payload, events)
+}
+
+#[test]
+fn decode_copy_matches_in_place() {
+    let input = b"\x89\x05\x48\x65\x6c\x6c\x6f\x8a\x85\x37\xfa\x21\x3d\x7f\x9f\x4d\x51\x58\x01\x03\x48\x65\x6c\x80\x02\x6c\x6f";
+    let (expected_payload, mut expected_events) = decode(input, None);
+    expected_events.retain(|e| !matches!(e, WebsocketFrameEvent::PayloadChunk { .. }));
+    for max_chunk_size in 1..=input.len() {
+        for output_size in [1, 2, 3, 7, 100] {
+            assert_eq!(decode_copy(input, max_chunk_size, output_size), (expected_payload.clone(), expected_events.clone()));
+        }
+    }
+
+    let mut d = frame_decoding::WebsocketFrameDecoder::new();
+    let ret = d.add_data_copy(b"\x82\x02ab", &mut []).unwrap();
+    assert_eq!(ret.consumed_bytes, 2);
+    let ret = d.add_data_copy(b"ab", &mut []).unwrap();
+    assert_eq!((ret.consumed_bytes, ret.produced_bytes, ret.event), (0, 0, Some(WebsocketFrameEvent::PayloadChunk { original_opcode: Opcode::Binary })));
+    let ret = d.add_data_copy(b"", &mut [0; 4]).unwrap();
+    assert_eq!((ret.consumed_bytes, ret.produced_bytes, ret.event), (0, 0, None));
+    let mut output = [0; 4];
+    let ret = d.add_data_copy(b"ab", &mut output).unwrap();
+    assert_eq!((ret.consumed_bytes, ret.produced_bytes), (2, 2));
+    assert_eq!(&output[..2], b"ab");
+}
+
+#[test]
+fn decode_events_iterator() {
+    let input = b"\x89\x05\x48\x65\x6c\x6c\x6f\x8a\x85\x37\xfa\x21\x3d\x7f\x9f\x4d\x51\x58\x01\x03\x48\x65\x6c\x80\x02\x6c\x6f";
+    for max_chunk_size in [1, 2, 5, input.len()] {
+        let mut d = frame_decoding::WebsocketFrameDecoder::new();
+        let mut payload = Vec::new();
+        let mut events = Vec::new();
+        let mut buf: Vec<u8> = input[..].into();
+        for chunk in buf.chunks_mut(max_chunk_size) {
+            for ev in d.events(chunk) {
+                let (ev, chunk_payload) = ev.unwrap();
+                if !matches!(ev, WebsocketFrameEvent::PayloadChunk { .. }) {
+                    assert!(chunk_payload.is_empty());
+                }
+                payload.extend_from_slice(chunk_payload);
+                events.push(ev);
+            }
+        }
+        assert_eq!((payload, events), decode(input, Some(max_chunk_size)));
+        assert!(d.eof_valid());
+    }
+
+    let mut d = frame_decoding::WebsocketFrameDecoder::new();
+    let mut input = *b"\x82\x01a\x82\x01b";
+    let mut events = d.events(&mut input);
+    assert!(matches!(events.next(), Some(Ok((WebsocketFrameEvent::Start { .. }, _)))));
+    let (ev, payload) = events.next().unwrap().unwrap();
+    assert!(matches!(ev, WebsocketFrameEvent::PayloadChunk { .. }));
+    assert_eq!(payload, b"a");
+    assert_eq!(events.into_rest(), b"\x82\x01b");
+}
+
+fn decode_strict(input: &[u8], strictness: FrameDecoderStrictness) -> Result<(), FrameDecoderError> {
+    let mut input : Vec<u8> = input.into();
+    let mut d = WebsocketFrameDecoder::with_strictness(strictness);
+    for ev in d.events(&mut input) {
+        ev?;
+    }
+    Ok(())
+}
+
+#[test]
+fn decode_strictness_options() {
+    let lenient = FrameDecoderStrictness::default();
+    let all = FrameDecoderStrictness {
+        reject_non_minimal_length: true,
+        reject_length_msb: true,
+        reject_oversized_control_frames: true,
+        reject_reserved_bits: true,
+    };
+    let inputs : &[(&[u8], FrameDecoderError)] = &[
+        (b"\x82\x7E\x00\x05hello", FrameDecoderError::NonMinimalLength),
+        (b"\x89\x7E\x00\x7Epp", FrameDecoderError::OversizedControlFrame),
+        (b"\xC1\x00", FrameDecoderError::ReservedBits),
+    ];
+    for (input, error) in inputs {
+        assert_eq!(decode_strict(input, lenient), Ok(()));
+        assert_eq!(decode_strict(input, all), Err(*error));
+    }
+    assert_eq!(decode_strict(b"\x82\x7E\x00\x7Ehello", all), Ok(()));
+
+    let only_rsv = FrameDecoderStrictness { reject_reserved_bits: true, ..lenient };
+    assert_eq!(decode_strict(b"\x82\x7E\x00\x05hello", only_rsv), Ok(()));
+    assert_eq!(decode_strict(b"\xC1\x00", only_rsv), Err(FrameDecoderError::ReservedBits));
+}
+
+#[cfg(feature="large_frames")]
+#[test]
+fn decode_strictness_64bit_length() {
+    let all = FrameDecoderStrictness {
+        reject_non_minimal_length: true,
+        reject_length_msb: true,
+        reject_oversized_control_frames: true,
+        reject_reserved_bits: true,
+    };
+    assert_eq!(decode_strict(b"\x82\x7F\x00\x00\x00\x00\x00\x00\x00\x05hello", FrameDecoderStrictness::default()), Ok(()));
+    assert_eq!(decode_strict(b"\x82\x7F\x00\x00\x00\x00\x00\x00\x00\x05hello", all), Err(FrameDecoderError::NonMinimalLength));
+    assert_eq!(decode_strict(b"\x82\x7F\x80\x00\x00\x00\x00\x00\x00\x05", all), Err(FrameDecoderError::LengthMsbSet));
+}
+
+#[cfg(not(feature="large_frames"))]
+#[test]
+fn decode_64bit_length_unsupported() {
+    assert_eq!(decode_strict(b"\x82\x7F\x00\x00\x00\x00\x00\x00\x00\x05hello", FrameDecoderStrictness::default()), Err(FrameDecoderError::ExceededFrameSize));
+}
+
+/// Feed frame header in chunks of `mcs` bytes, returning `FrameInfo` from the `Start` event.
+fn decode_header(input: &[u8], mcs: usize, mut d: WebsocketFrameDecoder) -> Result<FrameInfo, FrameDecoderError> {
+    let mut input : Vec<u8> = input.into();
+    for chunk in input.chunks_mut(mcs) {
+        let ret = d.add_data(chunk)?;
+        if let Some(WebsocketFrameEvent::Start { frame_info, .. }) = ret.event {
+            return Ok(frame_info);
+        }
+        assert_eq!(ret.consumed_bytes, chunk.len());
+    }
+    panic!("no frame start in {input:?}");
+}
+
+/// Frame headers with payload length encoded in a non-canonical way, lengths they denote and errors of strict decoder.
+const NON_CANONICAL_HEADERS : &[(&[u8], PayloadLength, FrameDecoderError)] = &[
+    (b"\x82\x7E\x00\x00", 0, FrameDecoderError::NonMinimalLength),
+    (b"\x82\x7E\x00\x01", 1, FrameDecoderError::NonMinimalLength),
+    (b"\x81\x7E\x00\x7D", 125, FrameDecoderError::NonMinimalLength),
+    (b"\x02\xFE\x00\x05\x01\x02\x03\x04", 5, FrameDecoderError::NonMinimalLength),
+    (b"\x89\x7E\x00\x7E", 126, FrameDecoderError::OversizedControlFrame),
+    (b"\x8A\x7E\xFF\xFF", 65535, FrameDecoderError::OversizedControlFrame),
+];
+
+#[cfg(feature="large_frames")]
+const NON_CANONICAL_HEADERS_64 : &[(&[u8], PayloadLength, FrameDecoderError)] = &[
+    (b"\x82\x7F\x00\x00\x00\x00\x00\x00\x00\x00", 0, FrameDecoderError::NonMinimalLength),
+    (b"\x82\x7F\x00\x00\x00\x00\x00\x00\x00\x7D", 125, FrameDecoderError::NonMinimalLength),
+    (b"\x82\x7F\x00\x00\x00\x00\x00\x00\x00\x7E", 126, FrameDecoderError::NonMinimalLength),
+    (b"\x80\x7F\x00\x00\x00\x00\x00\x00\xFF\xFF", 65535, FrameDecoderError::NonMinimalLength),
+    (b"\x82\xFF\x00\x00\x00\x00\x00\x00\xFF\xFF\x01\x02\x03\x04", 65535, FrameDecoderError::NonMinimalLength),
+    (b"\x82\x7F\x80\x00\x00\x00\x00\x00\x00\x00", 1 << 63, FrameDecoderError::LengthMsbSet),
+    (b"\x82\x7F\x80\x00\x00\x00\x00\x00\x00\x05", (1 << 63) + 5, FrameDecoderError::LengthMsbSet),
+    (b"\x82\x7F\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF", u64::MAX, FrameDecoderError::LengthMsbSet),
+    (b"\x89\x7F\x00\x00\x00\x00\x00\x01\x00\x00", 65536, FrameDecoderError::OversizedControlFrame),
+];
+
+/// Frame headers which are canonical and must be accepted by strict decoder.
+const CANONICAL_HEADERS : &[(&[u8], PayloadLength)] = &[
+    (b"\x82\x00", 0),
+    (b"\x82\x7D", 125),
+    (b"\x89\x7D", 125),
+    (b"\x82\xFD\x01\x02\x03\x04", 125),
+    (b"\x82\x7E\x00\x7E", 126),
+    (b"\x82\x7E\xFF\xFF", 65535),
+    (b"\xC1\x05", 5),
+];
+
+#[cfg(feature="large_frames")]
+const CANONICAL_HEADERS_64 : &[(&[u8], PayloadLength)] = &[
+    (b"\x82\x7F\x00\x00\x00\x00\x00\x01\x00\x00", 65536),
+    (b"\x82\x7F\x7F\xFF\xFF\xFF\xFF\xFF\xFF\xFF", u64::MAX >> 1),
+];
+
+#[test]
+fn decode_strict_corpus() {
+    let mut non_canonical = NON_CANONICAL_HEADERS.to_vec();
+    let mut canonical = CANONICAL_HEADERS.to_vec();
+    #[cfg(feature="large_frames")]
+    {
+        non_canonical.extend_from_slice(NON_CANONICAL_HEADERS_64);
+        canonical.extend_from_slice(CANONICAL_HEADERS_64);
+    }
+    for mcs in [1, 2, 3, 100] {
+        for &(header, length, error) in &non_canonical {
+            let lenient = decode_header(header, mcs, WebsocketFrameDecoder::new());
+            assert_eq!(lenient.map(|fi| fi.payload_length), Ok(length), "{header:x?}");
+            assert_eq!(decode_header(header, mcs, WebsocketFrameDecoder::new_strict()), Err(error), "{header:x?}");
+        }
+        for &(header, length) in &canonical {
+            let strict = decode_header(header, mcs, WebsocketFrameDecoder::new_strict());
+            assert_eq!(strict.map(|fi| fi.payload_length), Ok(length), "{header:x?}");
+        }
+    }
+}
+
+#[cfg(not(feature="large_frames"))]
+#[test]
+fn decode_strict_64bit_length_unsupported() {
+    let header = b"\x82\x7F\x00\x00\x00\x00\x00\x01\x00\x00";
+    assert_eq!(decode_header(header, 100, WebsocketFrameDecoder::new_strict()), Err(FrameDecoderError::ExceededFrameSize));
+}
+
+#[test]
+fn decode_unconsume_payload_in_place() {
+    let original = *b"\x81\x85\x37\xfa\x21\x3d\x7f\x9f\x4d\x51\x58";
+    let mut input = original;
+    let mut d = WebsocketFrameDecoder::new();
+    let ret = d.add_data(&mut input).unwrap();
+    assert!(matches!(ret.event, Some(WebsocketFrameEvent::Start { .. })));
+    let header_len = ret.consumed_bytes;
+    assert!(!d.unconsume_payload(1));
+
+    let buf = &mut input[header_len..];
+    let ret = d.add_data(buf).unwrap();
+    assert_eq!(ret.consumed_bytes, 5);
+    assert_eq!(&buf[..5], b"Hello");
+    // Application accepts only "He", the rest is given back.
+    assert!(!d.unconsume_payload_in_place(&mut [0u8; 6]));
+    assert!(d.unconsume_payload_in_place(&mut buf[2..5]));
+    assert_eq!(&buf[2..5], &original[header_len + 2..]);
+
+    let (payload, events) = {
+        let mut payload = Vec::new();
+        let mut events = Vec::new();
+        decode_chunk(&mut d, &mut buf[2..5], &mut payload, &mut events);
+        (payload, events)
+    };
+    assert_eq!(payload, b"llo");
+    assert!(matches!(events.last(), Some(WebsocketFrameEvent::End { .. })));
+    assert!(!d.unconsume_payload(1));
+}
+
+#[test]
+fn decode_unconsume_payload_copy() {
+    for input in [&b"\x82\x85\x37\xfa\x21\x3d\x7f\x9f\x4d\x51\x58"[..], &b"\x82\x05Hello"[..]] {
+        let mut d = WebsocketFrameDecoder::new();
+        let mut output = [0u8; 8];
+        let mut payload = Vec::new();
+        let mut offset = 0;
+        loop {
+            let ret = d.add_data_copy(&input[offset..], &mut output).unwrap();
+            offset += ret.consumed_bytes;
+            // Take only one byte of each chunk.
+            if ret.produced_bytes > 1 {
+                assert!(d.unconsume_payload(ret.produced_bytes - 1));
+                offset -= ret.produced_bytes - 1;
+            }
+            payload.extend_from_slice(&output[..ret.produced_bytes.min(1)]);
+            if let Some(WebsocketFrameEvent::End { .. }) = ret.event {
+                break;
+            }
+        }
+        assert_eq!(payload, b"Hello");
+    }
+}
@@ -0,0 +1,514 @@
+use core::ops::Range;
+
+use tinyvec::ArrayVec;
+
+use crate::connection::{Connection, ConnectionEvent};
+use crate::{
+    CloseHandshakeError, ClosePayload, ClosePayloadError, DecoderLimits, FrameInfo,
+    MaskingKeySource, Opcode, PayloadLength, ProtocolViolation, Role, WebsocketMessageEvent,
+    MAX_CONTROL_PAYLOAD_LENGTH, MAX_HEADER_LENGTH,
+};
+
+/// Size of the stack buffer used to mask outgoing payload.
+const MASKING_CHUNK_SIZE: usize = 64;
+
+/// Error from [`EmbeddedWebSocket`] or [`EmbeddedAsyncWebSocket`].
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum EmbeddedWebSocketError<E> {
+    /// Underlying transport failed.
+    Io(E),
+    /// Peer violated the protocol. Close frame with [`ProtocolViolation::close_code`] is already sent.
+    ProtocolViolation(ProtocolViolation),
+    /// Transport ended without a closing handshake or in the middle of a message.
+    UnexpectedEof,
+    /// Incoming message does not fit in the supplied buffer (the rest of it is skipped),
+    /// or outgoing payload is too long for [`PayloadLength`].
+    MessageTooLarge,
+    /// Frame must not be sent at this stage of the closing handshake.
+    CloseHandshake(CloseHandshakeError),
+    /// Payload passed to `close` cannot be encoded into a valid Close frame.
+    InvalidClosePayload(ClosePayloadError),
+}
+
+/// Incoming message-level event together with its payload.
+type EventResult<'a, E> =
+    Result<Option<(WebsocketMessageEvent, &'a [u8])>, EmbeddedWebSocketError<E>>;
+
+/// Opcode and content of a complete incoming message.
+type MessageResult<'a, E> = Result<Option<(Opcode, &'a [u8])>, EmbeddedWebSocketError<E>>;
+
+enum Step {
+    Event(WebsocketMessageEvent, Range<usize>),
+    Closed,
+    NeedData,
+}
+
+/// State shared by blocking and async adapters: connection logic and incoming data buffer.
+#[derive(Debug)]
+struct EmbeddedCore<K, const N: usize> {
+    connection: Connection<K>,
+    buf: [u8; N],
+    start: usize,
+    end: usize,
+    current_message: Option<Opcode>,
+    closed: bool,
+}
+
+impl<K: MaskingKeySource, const N: usize> EmbeddedCore<K, N> {
+    fn new(role: Role, key_source: K) -> Self {
+        EmbeddedCore {
+            connection: Connection::new(role, key_source),
+            buf: [0; N],
+            start: 0,
+            end: 0,
+            current_message: None,
+            closed: false,
+        }
+    }
+
+    /// Process buffered data until there is an event or more data is needed.
+    ///
+    /// Queued control frames should be sent before acting on the result, even if it is an error.
+    fn step<E>(&mut self) -> Result<Step, EmbeddedWebSocketError<E>> {
+        loop {
+            if self.closed {
+                return Ok(Step::Closed);
+            }
+            let ret = self
+                .connection
+                .add_data(&mut self.buf[self.start..self.end])
+                .inspect_err(|_| self.closed = true)
+                .map_err(EmbeddedWebSocketError::ProtocolViolation)?;
+            let mut range = self.start..(self.start + ret.consumed_bytes);
+            self.start += ret.consumed_bytes;
+            if ret.event != Some(ConnectionEvent::Payload) {
+                range = self.start..self.start;
+            }
+            let event = match ret.event {
+                Some(ConnectionEvent::MessageStart(opcode)) => {
+                    self.current_message = Some(opcode);
+                    WebsocketMessageEvent::MessageStart { opcode }
+                }
+                Some(ConnectionEvent::Payload) => WebsocketMessageEvent::MessageChunk {
+                    opcode: self.current_message.unwrap_or_default(),
+                },
+                Some(ConnectionEvent::MessageEnd(opcode)) => {
+                    self.current_message = None;
+                    WebsocketMessageEvent::MessageEnd { opcode }
+                }
+                Some(ConnectionEvent::Closed) => {
+                    self.closed = true;
+                    return Ok(Step::Closed);
+                }
+                None if ret.consumed_bytes > 0 => continue,
+                None => return Ok(Step::NeedData),
+            };
+            return Ok(Step::Event(event, range));
+        }
+    }
+
+    /// Register `n` bytes read into [`EmbeddedCore::buf`].
+    fn filled<E>(&mut self, n: usize) -> Result<(), EmbeddedWebSocketError<E>> {
+        self.start = 0;
+        self.end = n;
+        if n == 0 {
+            self.closed = true;
+            if self.connection.eof().is_err() {
+                return Err(EmbeddedWebSocketError::UnexpectedEof);
+            }
+        }
+        Ok(())
+    }
+
+    fn start_frame<E>(
+        &mut self,
+        opcode: Opcode,
+        payload_len: usize,
+        fin: bool,
+    ) -> Result<ArrayVec<[u8; MAX_HEADER_LENGTH]>, EmbeddedWebSocketError<E>> {
+        let frame_info = FrameInfo {
+            opcode,
+            payload_length: PayloadLength::try_from(payload_len)
+                .map_err(|_| EmbeddedWebSocketError::MessageTooLarge)?,
+            mask: None,
+            fin,
+            reserved: 0,
+        };
+        self.connection
+            .start_frame(&frame_info)
+            .map_err(EmbeddedWebSocketError::CloseHandshake)
+    }
+
+    /// Mask the next piece of outgoing `payload` into `chunk`, advancing `payload` past it.
+    /// Returns `None` when the payload is exhausted.
+    fn next_payload_chunk<'c>(
+        &mut self,
+        payload: &mut &[u8],
+        chunk: &'c mut [u8; MASKING_CHUNK_SIZE],
+    ) -> Option<&'c [u8]> {
+        if payload.is_empty() {
+            return None;
+        }
+        let (piece, rest) = payload.split_at(payload.len().min(MASKING_CHUNK_SIZE));
+        *payload = rest;
+        let chunk = &mut chunk[..piece.len()];
+        chunk.copy_from_slice(piece);
+        self.connection.transform_frame_payload(chunk);
+        Some(chunk)
+    }
+}
+
+/// Encode payload for `close` methods.
+fn encode_close<E>(
+    payload: &ClosePayload<'_>,
+) -> Result<ArrayVec<[u8; MAX_CONTROL_PAYLOAD_LENGTH as usize]>, EmbeddedWebSocketError<E>> {
+    payload
+        .encode()
+        .map_err(EmbeddedWebSocketError::InvalidClosePayload)
+}
+
+/// Accumulates a message for `read_message` methods.
+struct MessageCollector {
+    opcode: Option<Opcode>,
+    len: usize,
+    overflow: bool,
+}
+
+impl MessageCollector {
+    const fn new() -> Self {
+        MessageCollector {
+            opcode: None,
+            len: 0,
+            overflow: false,
+        }
+    }
+
+    /// Handle a result of `read_event`. Returns `Some` when reading is finished:
+    /// with opcode and length of a complete message, or `None` if the connection is closed.
+    fn handle<E>(
+        &mut self,
+        event: Option<(WebsocketMessageEvent, &[u8])>,
+        out: &mut [u8],
+    ) -> Result<Option<Option<(Opcode, usize)>>, EmbeddedWebSocketError<E>> {
+        let Some((event, payload)) = event else {
+            if self.opcode.is_some() {
+                return Err(EmbeddedWebSocketError::UnexpectedEof);
+            }
+            return Ok(Some(None));
+        };
+        match event {
+            WebsocketMessageEvent::MessageStart { opcode } => {
+                *self = MessageCollector::new();
+                self.opcode = Some(opcode);
+            }
+            WebsocketMessageEvent::MessageChunk { .. } if self.opcode.is_some() => {
+                match out.get_mut(self.len..(self.len + payload.len())) {
+                    Some(dst) if !self.overflow => dst.copy_from_slice(payload),
+                    _ => self.overflow = true,
+                }
+                self.len += payload.len();
+            }
+            WebsocketMessageEvent::MessageEnd { .. } => {
+                if let Some(opcode) = self.opcode.take() {
+                    if self.overflow {
+                        return Err(EmbeddedWebSocketError::MessageTooLarge);
+                    }
+                    return Ok(Some(Some((opcode, self.len))));
+                }
+            }
+            _ => (),
+        }
+        Ok(None)
+    }
+}
+
+/// A WebSocket connection over blocking [`embedded_io::Read`] + [`embedded_io::Write`], after the handshake is done.
+///
+/// Requires `embedded_io` crate feature. Does not allocate: incoming data is buffered in an `N`-byte array
+/// and outgoing payload is masked in small chunks on stack.
+///
+/// Incoming frames are validated with [`crate::ValidatingFrameDecoder`]. Pings are replied to automatically
+/// and peer's Close frame is echoed back, after which reading methods return `None`.
+/// Masking keys (for [`Role::Client`]) are obtained from user-supplied [`MaskingKeySource`],
+/// typically backed by a hardware random number generator.
+#[cfg(feature = "embedded_io")]
+#[derive(Debug)]
+pub struct EmbeddedWebSocket<S, K, const N: usize> {
+    inner: S,
+    core: EmbeddedCore<K, N>,
+}
+
+#[cfg(feature = "embedded_io")]
+impl<S, K, const N: usize> EmbeddedWebSocket<S, K, N>
+where
+    S: embedded_io::Read + embedded_io::Write,
+    K: MaskingKeySource,
+{
+    /// Wrap a transport, which already passed the WebSocket handshake.
+    pub fn new(inner: S, role: Role, key_source: K) -> Self {
+        EmbeddedWebSocket {
+            inner,
+            core: EmbeddedCore::new(role, key_source),
+        }
+    }
+
+    fn flush_pending_output(&mut self) -> Result<(), EmbeddedWebSocketError<S::Error>> {
+        let pending = self.core.connection.pending_output();
+        if !pending.is_empty() {
+            let n = pending.len();
+            self.inner
+                .write_all(pending)
+                .map_err(EmbeddedWebSocketError::Io)?;
+            self.core.connection.consume_pending_output(n);
+            self.inner.flush().map_err(EmbeddedWebSocketError::Io)?;
+        }
+        Ok(())
+    }
+
+    /// Read the next message-level event, together with payload bytes for [`WebsocketMessageEvent::MessageChunk`].
+    ///
+    /// [`WebsocketMessageEvent::Control`] is never returned, control frames are handled internally.
+    /// Returns `None` when the connection is closed.
+    pub fn read_event(&mut self) -> EventResult<'_, S::Error> {
+        loop {
+            let step = self.core.step();
+            self.flush_pending_output()?;
+            match step? {
+                Step::Event(event, range) => return Ok(Some((event, &self.core.buf[range]))),
+                Step::Closed => return Ok(None),
+                Step::NeedData => {
+                    let n = self
+                        .inner
+                        .read(&mut self.core.buf)
+                        .map_err(EmbeddedWebSocketError::Io)?;
+                    self.core.filled(n)?;
+                }
+            }
+        }
+    }
+
+    /// Read the next complete data message into `out`, returning its opcode and content.
+    ///
+    /// Unread content of a previous message is skipped. Returns `None` when the connection is closed.
+    pub fn read_message<'b>(&mut self, out: &'b mut [u8]) -> MessageResult<'b, S::Error> {
+        let mut collector = MessageCollector::new();
+        loop {
+            let event = self.read_event()?;
+            if let Some(message) = collector.handle(event, out)? {
+                return Ok(message.map(|(opcode, len)| (opcode, &out[..len])));
+            }
+        }
+    }
+
+    /// Send a frame. Control frames queued by the connection are sent first.
+    ///
+    /// Use `fin=false` and [`Opcode::Continuation`] frames for messages of unknown length.
+    /// Peer's Close frame is echoed back as soon as it is read, after which such message cannot be continued:
+    /// further frames fail with [`EmbeddedWebSocketError::CloseHandshake`].
+    pub fn write_frame(
+        &mut self,
+        opcode: Opcode,
+        mut payload: &[u8],
+        fin: bool,
+    ) -> Result<(), EmbeddedWebSocketError<S::Error>> {
+        self.flush_pending_output()?;
+        let header = self.core.start_frame(opcode, payload.len(), fin)?;
+        self.inner
+            .write_all(&header)
+            .map_err(EmbeddedWebSocketError::Io)?;
+        let mut chunk = [0u8; MASKING_CHUNK_SIZE];
+        while let Some(masked) = self.core.next_payload_chunk(&mut payload, &mut chunk) {
+            self.inner
+                .write_all(masked)
+                .map_err(EmbeddedWebSocketError::Io)?;
+        }
+        Ok(())
+    }
+
+    /// Send a complete message in one frame and flush the transport.
+    pub fn write_message(
+        &mut self,
+        opcode: Opcode,
+        payload: &[u8],
+    ) -> Result<(), EmbeddedWebSocketError<S::Error>> {
+        self.write_frame(opcode, payload, true)?;
+        self.inner.flush().map_err(EmbeddedWebSocketError::Io)
+    }
+
+    /// Initiate the closing handshake. Keep reading until `None` is returned to receive peer's reply.
+    pub fn close(
+        &mut self,
+        payload: &ClosePayload<'_>,
+    ) -> Result<(), EmbeddedWebSocketError<S::Error>> {
+        let payload = encode_close(payload)?;
+        self.write_message(Opcode::ConnectionClose, &payload)
+    }
+
+    /// Payload of the Close frame received from peer, if any.
+    pub fn peer_close(&self) -> Option<ClosePayload<'_>> {
+        self.core.connection.peer_close()
+    }
+
+    /// Set resource limits for incoming frames, which are unlimited by default.
+    /// Exceeding them fails the connection with [`crate::CloseCode::MessageTooBig`].
+    pub fn set_limits(&mut self, limits: DecoderLimits) {
+        self.core.connection.set_limits(limits)
+    }
+
+    /// Get reference to the underlying transport.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get mutable reference to the underlying transport. Reading or writing it directly would likely corrupt the WebSocket connection.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Unwrap the underlying transport.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+/// A WebSocket connection over [`embedded_io_async::Read`] + [`embedded_io_async::Write`]
+/// (e.g. embassy-net `TcpSocket`), after the handshake is done.
+///
+/// Requires `embedded_io_async` crate feature. Works the same way as [`EmbeddedWebSocket`], but with async methods.
+#[cfg(feature = "embedded_io_async")]
+#[derive(Debug)]
+pub struct EmbeddedAsyncWebSocket<S, K, const N: usize> {
+    inner: S,
+    core: EmbeddedCore<K, N>,
+}
+
+#[cfg(feature = "embedded_io_async")]
+impl<S, K, const N: usize> EmbeddedAsyncWebSocket<S, K, N>
+where
+    S: embedded_io_async::Read + embedded_io_async::Write,
+    K: MaskingKeySource,
+{
+    /// Wrap a transport, which already passed the WebSocket handshake.
+    pub fn new(inner: S, role: Role, key_source: K) -> Self {
+        EmbeddedAsyncWebSocket {
+            inner,
+            core: EmbeddedCore::new(role, key_source),
+        }
+    }
+
+    async fn flush_pending_output(&mut self) -> Result<(), EmbeddedWebSocketError<S::Error>> {
+        let pending = self.core.connection.pending_output();
+        if !pending.is_empty() {
+            let n = pending.len();
+            self.inner
+                .write_all(pending)
+                .await
+                .map_err(EmbeddedWebSocketError::Io)?;
+            self.core.connection.consume_pending_output(n);
+            self.inner
+                .flush()
+                .await
+                .map_err(EmbeddedWebSocketError::Io)?;
+        }
+        Ok(())
+    }
+
+    /// Read the next message-level event, see [`EmbeddedWebSocket::read_event`].
+    pub async fn read_event(&mut self) -> EventResult<'_, S::Error> {
+        loop {
+            let step = self.core.step();
+            self.flush_pending_output().await?;
+            match step? {
+                Step::Event(event, range) => return Ok(Some((event, &self.core.buf[range]))),
+                Step::Closed => return Ok(None),
+                Step::NeedData => {
+                    let n = self
+                        .inner
+                        .read(&mut self.core.buf)
+                        .await
+                        .map_err(EmbeddedWebSocketError::Io)?;
+                    self.core.filled(n)?;
+                }
+            }
+        }
+    }
+
+    /// Read the next complete data message into `out`, see [`EmbeddedWebSocket::read_message`].
+    pub async fn read_message<'b>(&mut self, out: &'b mut [u8]) -> MessageResult<'b, S::Error> {
+        let mut collector = MessageCollector::new();
+        loop {
+            let event = self.read_event().await?;
+            if let Some(message) = collector.handle(event, out)? {
+                return Ok(message.map(|(opcode, len)| (opcode, &out[..len])));
+            }
+        }
+    }
+
+    /// Send a frame, see [`EmbeddedWebSocket::write_frame`].
+    pub async fn write_frame(
+        &mut self,
+        opcode: Opcode,
+        mut payload: &[u8],
+        fin: bool,
+    ) -> Result<(), EmbeddedWebSocketError<S::Error>> {
+        self.flush_pending_output().await?;
+        let header = self.core.start_frame(opcode, payload.len(), fin)?;
+        self.inner
+            .write_all(&header)
+            .await
+            .map_err(EmbeddedWebSocketError::Io)?;
+        let mut chunk = [0u8; MASKING_CHUNK_SIZE];
+        while let Some(masked) = self.core.next_payload_chunk(&mut payload, &mut chunk) {
+            self.inner
+                .write_all(masked)
+                .await
+                .map_err(EmbeddedWebSocketError::Io)?;
+        }
+        Ok(())
+    }
+
+    /// Send a complete message in one frame and flush the transport.
+    pub async fn write_message(
+        &mut self,
+        opcode: Opcode,
+        payload: &[u8],
+    ) -> Result<(), EmbeddedWebSocketError<S::Error>> {
+        self.write_frame(opcode, payload, true).await?;
+        self.inner.flush().await.map_err(EmbeddedWebSocketError::Io)
+    }
+
+    /// Initiate the closing handshake. Keep reading until `None` is returned to receive peer's reply.
+    pub async fn close(
+        &mut self,
+        payload: &ClosePayload<'_>,
+    ) -> Result<(), EmbeddedWebSocketError<S::Error>> {
+        let payload = encode_close(payload)?;
+        self.write_message(Opcode::ConnectionClose, &payload).await
+    }
+
+    /// Payload of the Close frame received from peer, if any.
+    pub fn peer_close(&self) -> Option<ClosePayload<'_>> {
+        self.core.connection.peer_close()
+    }
+
+    /// Set resource limits for incoming frames, which are unlimited by default.
+    /// Exceeding them fails the connection with [`crate::CloseCode::MessageTooBig`].
+    pub fn set_limits(&mut self, limits: DecoderLimits) {
+        self.core.connection.set_limits(limits)
+    }
+
+    /// Get reference to the underlying transport.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get mutable reference to the underlying transport. Reading or writing it directly would likely corrupt the WebSocket connection.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Unwrap the underlying transport.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
@@ -0,0 +1,302 @@
+use super::*;
+
+extern crate std;
+
+use std::vec::Vec;
+
+use pretty_assertions::assert_eq;
+
+use crate::test_util::{close_payload, frame, frames};
+use crate::{CloseCode, Opcode, Role};
+#[cfg(feature = "embedded_io")]
+use crate::{
+    CloseHandshakeError, ClosePayload, ClosePayloadError, DecoderLimit, DecoderLimits,
+    ProtocolViolation, WebsocketMessageEvent,
+};
+
+/// In-memory transport: reads from prepared input in small portions, collects output.
+struct Mock {
+    input: Vec<u8>,
+    position: usize,
+    output: Vec<u8>,
+}
+
+impl Mock {
+    fn new(input: &[&[u8]]) -> Self {
+        Mock {
+            input: input.concat(),
+            position: 0,
+            output: Vec::new(),
+        }
+    }
+
+    fn read_portion(&mut self, buf: &mut [u8]) -> usize {
+        let n = buf.len().min(3).min(self.input.len() - self.position);
+        buf[..n].copy_from_slice(&self.input[self.position..(self.position + n)]);
+        self.position += n;
+        n
+    }
+}
+
+#[cfg(feature = "embedded_io")]
+impl embedded_io::ErrorType for Mock {
+    type Error = core::convert::Infallible;
+}
+
+#[cfg(feature = "embedded_io")]
+impl embedded_io::Read for Mock {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Ok(self.read_portion(buf))
+    }
+}
+
+#[cfg(feature = "embedded_io")]
+impl embedded_io::Write for Mock {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.output.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "embedded_io_async", not(feature = "embedded_io")))]
+impl embedded_io_async::ErrorType for Mock {
+    type Error = core::convert::Infallible;
+}
+
+#[cfg(feature = "embedded_io_async")]
+impl embedded_io_async::Read for Mock {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Ok(self.read_portion(buf))
+    }
+}
+
+#[cfg(feature = "embedded_io_async")]
+impl embedded_io_async::Write for Mock {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.output.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+}
+
+const MASK: Option<[u8; 4]> = Some([1, 2, 3, 4]);
+
+fn server_input() -> Vec<Vec<u8>> {
+    std::vec![
+        frame(Opcode::Text, false, MASK, b"Hello, "),
+        frame(Opcode::Ping, true, MASK, b"p"),
+        frame(Opcode::Continuation, true, MASK, b"world"),
+        frame(Opcode::Binary, true, MASK, &[0x55; 20]),
+        frame(Opcode::Binary, true, MASK, b"ok"),
+        frame(
+            Opcode::ConnectionClose,
+            true,
+            MASK,
+            &close_payload(CloseCode::Normal)
+        ),
+    ]
+}
+
+fn expected_server_output() -> Vec<(Opcode, bool, bool, Vec<u8>)> {
+    std::vec![
+        (Opcode::Pong, true, false, b"p".to_vec()),
+        (
+            Opcode::ConnectionClose,
+            true,
+            false,
+            close_payload(CloseCode::Normal)
+        ),
+    ]
+}
+
+#[cfg(feature = "embedded_io")]
+#[test]
+fn embedded_server_reads_messages() {
+    let input = server_input();
+    let input: Vec<&[u8]> = input.iter().map(|x| &x[..]).collect();
+    let mut ws = EmbeddedWebSocket::<_, _, 16>::new(Mock::new(&input), Role::Server, || [0; 4]);
+
+    let (event, payload) = ws.read_event().unwrap().unwrap();
+    assert_eq!(
+        event,
+        WebsocketMessageEvent::MessageStart {
+            opcode: Opcode::Text
+        }
+    );
+    assert_eq!(payload, b"");
+    let mut text = Vec::new();
+    loop {
+        match ws.read_event().unwrap().unwrap() {
+            (WebsocketMessageEvent::MessageChunk { opcode }, payload) => {
+                assert_eq!(opcode, Opcode::Text);
+                text.extend_from_slice(payload);
+            }
+            (WebsocketMessageEvent::MessageEnd { opcode }, _) => {
+                assert_eq!(opcode, Opcode::Text);
+                break;
+            }
+            (ev, _) => panic!("unexpected event {ev:?}"),
+        }
+    }
+    assert_eq!(text, b"Hello, world");
+
+    let mut out = [0u8; 8];
+    assert_eq!(
+        ws.read_message(&mut out),
+        Err(EmbeddedWebSocketError::MessageTooLarge)
+    );
+    assert_eq!(
+        ws.read_message(&mut out).unwrap(),
+        Some((Opcode::Binary, &b"ok"[..]))
+    );
+    assert_eq!(ws.read_message(&mut out).unwrap(), None);
+    assert_eq!(ws.peer_close().unwrap().code, Some(CloseCode::Normal));
+    assert_eq!(ws.get_ref().position, ws.get_ref().input.len());
+    assert_eq!(frames(ws.into_inner().output), expected_server_output());
+}
+
+#[cfg(feature = "embedded_io")]
+#[test]
+fn embedded_client_writes_messages() {
+    let close = frame(
+        Opcode::ConnectionClose,
+        true,
+        None,
+        &close_payload(CloseCode::Normal),
+    );
+    let mut ws =
+        EmbeddedWebSocket::<_, _, 16>::new(Mock::new(&[&close]), Role::Client, || [5, 6, 7, 8]);
+    ws.write_message(Opcode::Text, b"hello").unwrap();
+    let long = [0xAA; 100];
+    ws.write_frame(Opcode::Binary, &long, false).unwrap();
+    ws.write_frame(Opcode::Continuation, b"", true).unwrap();
+    assert_eq!(
+        ws.close(&ClosePayload::new(CloseCode::NoStatusReceived)),
+        Err(EmbeddedWebSocketError::InvalidClosePayload(
+            ClosePayloadError::InvalidCode(CloseCode::NoStatusReceived)
+        ))
+    );
+    ws.close(&ClosePayload::new(CloseCode::Normal)).unwrap();
+    assert_eq!(
+        ws.write_message(Opcode::Text, b"late"),
+        Err(EmbeddedWebSocketError::CloseHandshake(
+            CloseHandshakeError::DataAfterClose
+        ))
+    );
+    assert_eq!(ws.read_message(&mut [0u8; 8]).unwrap(), None);
+    assert_eq!(
+        frames(ws.into_inner().output),
+        std::vec![
+            (Opcode::Text, true, true, b"hello".to_vec()),
+            (Opcode::Binary, false, true, long.to_vec()),
+            (Opcode::Continuation, true, true, Vec::new()),
+            (
+                Opcode::ConnectionClose,
+                true,
+                true,
+                close_payload(CloseCode::Normal)
+            ),
+        ]
+    );
+}
+
+#[cfg(feature = "embedded_io")]
+#[test]
+fn embedded_failures() {
+    // Unmasked frame from client fails the connection with Protocol Error.
+    let unmasked = frame(Opcode::Text, true, None, b"x");
+    let mut ws =
+        EmbeddedWebSocket::<_, _, 16>::new(Mock::new(&[&unmasked]), Role::Server, || [0; 4]);
+    assert!(matches!(
+        ws.read_message(&mut [0u8; 8]),
+        Err(EmbeddedWebSocketError::ProtocolViolation(_))
+    ));
+    assert_eq!(ws.read_message(&mut [0u8; 8]).unwrap(), None);
+    assert_eq!(
+        frames(ws.into_inner().output),
+        std::vec![(
+            Opcode::ConnectionClose,
+            true,
+            false,
+            close_payload(CloseCode::ProtocolError)
+        )]
+    );
+
+    // Connection ends in the middle of a message.
+    let partial = frame(Opcode::Text, false, MASK, b"x");
+    let mut ws =
+        EmbeddedWebSocket::<_, _, 16>::new(Mock::new(&[&partial]), Role::Server, || [0; 4]);
+    assert_eq!(
+        ws.read_message(&mut [0u8; 8]),
+        Err(EmbeddedWebSocketError::UnexpectedEof)
+    );
+
+    // Frame exceeding the limit fails the connection with Message Too Big.
+    let big = frame(Opcode::Binary, true, MASK, b"12345");
+    let mut ws = EmbeddedWebSocket::<_, _, 16>::new(Mock::new(&[&big]), Role::Server, || [0; 4]);
+    ws.set_limits(DecoderLimits {
+        max_frame_payload: 4,
+        ..DecoderLimits::unlimited()
+    });
+    assert_eq!(
+        ws.read_message(&mut [0u8; 8]),
+        Err(EmbeddedWebSocketError::ProtocolViolation(
+            ProtocolViolation::LimitExceeded(DecoderLimit::FramePayload)
+        ))
+    );
+    assert_eq!(
+        frames(ws.into_inner().output),
+        std::vec![(
+            Opcode::ConnectionClose,
+            true,
+            false,
+            close_payload(CloseCode::MessageTooBig)
+        )]
+    );
+}
+
+/// Run a future which never waits, as [`Mock`] is always ready.
+#[cfg(feature = "embedded_io_async")]
+fn block_on<F: core::future::Future>(f: F) -> F::Output {
+    let mut f = core::pin::pin!(f);
+    let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+    match f.as_mut().poll(&mut cx) {
+        core::task::Poll::Ready(x) => x,
+        core::task::Poll::Pending => panic!("Mock transport should never block"),
+    }
+}
+
+#[cfg(feature = "embedded_io_async")]
+#[test]
+fn embedded_async_server() {
+    block_on(embedded_async_server_inner());
+}
+
+#[cfg(feature = "embedded_io_async")]
+async fn embedded_async_server_inner() {
+    let input = server_input();
+    let input: Vec<&[u8]> = input.iter().map(|x| &x[..]).collect();
+    let mut ws =
+        EmbeddedAsyncWebSocket::<_, _, 16>::new(Mock::new(&input), Role::Server, || [0; 4]);
+    let mut out = [0u8; 32];
+    assert_eq!(
+        ws.read_message(&mut out).await.unwrap(),
+        Some((Opcode::Text, &b"Hello, world"[..]))
+    );
+    assert_eq!(
+        ws.read_message(&mut out).await.unwrap(),
+        Some((Opcode::Binary, &[0x55; 20][..]))
+    );
+    ws.write_message(Opcode::Binary, b"reply").await.unwrap();
+    assert_eq!(
+        ws.read_message(&mut out).await.unwrap(),
+        Some((Opcode::Binary, &b"ok"[..]))
+    );
+    assert_eq!(ws.read_message(&mut out).await.unwrap(), None);
+    let mut expected = expected_server_output();
+    expected.insert(1, (Opcode::Binary, true, false, b"reply".to_vec()));
+    assert_eq!(frames(ws.into_inner().output), expected);
+}
@@ -0,0 +1,86 @@
+extern crate std;
+
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+const HELLO: FrameInfo = FrameInfo {
+    opcode: Opcode::Text,
+    payload_length: 5,
+    mask: None,
+    fin: true,
+    reserved: 0,
+};
+
+#[test]
+fn encode_with_role() {
+    let mut keys = || *b"\x37\xfa\x21\x3d";
+
+    let mut e = WebsocketFrameEncoder::with_role(Role::Client);
+    assert_eq!(&e.start_frame_with_key_source(&HELLO, &mut keys)[..], b"\x81\x85\x37\xfa\x21\x3d");
+    let mut payload = *b"Hello";
+    e.transform_frame_payload(&mut payload[..]);
+    assert_eq!(&payload, b"\x7f\x9f\x4d\x51\x58");
+
+    let mut e = WebsocketFrameEncoder::with_role(Role::Server);
+    let masked = FrameInfo { mask: Some([1, 2, 3, 4]), ..HELLO };
+    assert_eq!(&e.start_frame_with_key_source(&masked, &mut keys)[..], b"\x81\x05");
+    assert!(!e.transform_needed());
+
+    let mut e = WebsocketFrameEncoder::new();
+    assert_eq!(&e.start_frame_with_key_source(&masked, &mut keys)[..], b"\x81\x85\x01\x02\x03\x04");
+}
+
+#[test]
+fn encode_masking_mismatch() {
+    let masked = FrameInfo { mask: Some([1, 2, 3, 4]), ..HELLO };
+    assert_eq!(WebsocketFrameEncoder::with_role(Role::Client).start_frame(&HELLO), Err(FrameEncoderError::MaskingMismatch));
+    assert_eq!(WebsocketFrameEncoder::with_role(Role::Server).start_frame(&masked), Err(FrameEncoderError::MaskingMismatch));
+    assert_eq!(&WebsocketFrameEncoder::with_role(Role::Client).start_frame(&masked).unwrap()[..], b"\x81\x85\x01\x02\x03\x04");
+    assert_eq!(&WebsocketFrameEncoder::with_role(Role::Server).start_frame(&HELLO).unwrap()[..], b"\x81\x05");
+
+    let mut w = WebsocketFrameWriter::with_role(Role::Client);
+    assert_eq!(w.start_frame(&HELLO), Err(FrameEncoderError::MaskingMismatch));
+    assert!(w.frame_finished());
+}
+
+fn write_all(w: &mut WebsocketFrameWriter, mut input: &[u8], out_chunk: usize) -> std::vec::Vec<u8> {
+    let mut ret = std::vec::Vec::new();
+    let mut buf = std::vec![0u8; out_chunk];
+    while !w.frame_finished() {
+        let r = w.write(input, &mut buf);
+        input = &input[r.consumed_bytes..];
+        ret.extend_from_slice(&buf[..r.produced_bytes]);
+    }
+    assert!(input.is_empty());
+    ret
+}
+
+#[test]
+fn frame_writer() {
+    let payload: std::vec::Vec<u8> = (0..300u16).map(|x| x as u8).collect();
+    let fi = FrameInfo { opcode: Opcode::Binary, payload_length: 300, mask: Some([9, 8, 7, 6]), fin: true, reserved: 0 };
+
+    let mut e = WebsocketFrameEncoder::new();
+    let mut expected: std::vec::Vec<u8> = e.start_frame(&fi).unwrap().to_vec();
+    let mut masked = payload.clone();
+    e.transform_frame_payload(&mut masked);
+    expected.extend_from_slice(&masked);
+
+    for out_chunk in [1, 3, 5, 64, 1000] {
+        let mut w = WebsocketFrameWriter::new();
+        w.start_frame(&fi).unwrap();
+        assert!(!w.frame_finished());
+        assert_eq!(write_all(&mut w, &payload, out_chunk), expected);
+        assert_eq!(w.remaining_payload(), 0);
+    }
+
+    let mut w = WebsocketFrameWriter::with_role(Role::Server);
+    w.start_frame_with_key_source(&FrameInfo { payload_length: 2, ..fi }, &mut || [1, 2, 3, 4]);
+    let mut buf = [0u8; 16];
+    let r = w.write(b"abcdef", &mut buf);
+    assert_eq!(r, WebsocketFrameWriterResult { consumed_bytes: 2, produced_bytes: 4 });
+    assert_eq!(&buf[..4], b"\x82\x02ab");
+    assert!(w.frame_finished());
+    assert_eq!(w.write(b"cdef", &mut buf).produced_bytes, 0);
+}
@@ -0,0 +1,104 @@
+use crate::{FrameInfo, Opcode};
+
+/// RSV1 bit, as found in [`FrameInfo::reserved`].
+pub const RSV1: u8 = 0b100;
+/// RSV2 bit, as found in [`FrameInfo::reserved`].
+pub const RSV2: u8 = 0b010;
+/// RSV3 bit, as found in [`FrameInfo::reserved`].
+pub const RSV3: u8 = 0b001;
+
+/// A WebSocket extension which gives meaning to reserved bits or reserved opcodes.
+///
+/// Register it with [`crate::ValidatingFrameDecoder::allow_extension`] to make the claimed
+/// bits and opcodes legal in incoming frames.
+///
+/// Payload transformations work in place, so only length-preserving transformations can be
+/// expressed with them. Call [`Extension::decode_payload`] on unmasked payload of each
+/// [`crate::WebsocketFrameEvent::PayloadChunk`] and [`Extension::encode_payload`] on outgoing
+/// payload before [`crate::WebsocketFrameEncoder::transform_frame_payload`].
+///
+/// Extensions can be chained using tuples: `(A, B)` claims everything that `A` or `B` claims,
+/// encodes payload with `A`, then `B` and decodes it in reverse order.
+pub trait Extension {
+    /// RSV bits which may be set in frames when this extension is active, e.g. [`RSV1`].
+    ///
+    /// See [`Extension::reserved_bits_for_opcode`] for which frames may actually carry them.
+    fn reserved_bits(&self) -> u8 {
+        0
+    }
+
+    /// RSV bits which may be set in frames with given opcode.
+    ///
+    /// By default, bits from [`Extension::reserved_bits`] are allowed in data frames (including
+    /// [`Opcode::Continuation`]) and not in control frames.
+    fn reserved_bits_for_opcode(&self, opcode: Opcode) -> u8 {
+        if opcode.is_data() {
+            self.reserved_bits()
+        } else {
+            0
+        }
+    }
+
+    /// Whether this extension defines a meaning for given reserved opcode, e.g. [`Opcode::ReservedData3`].
+    ///
+    /// Claimed data opcodes (`ReservedData3` to `ReservedData7`) start messages like [`Opcode::Binary`] does.
+    /// Claimed control opcodes (`ReservedControlB` to `ReservedControlF`) are subject to the same rules as [`Opcode::Ping`].
+    fn claims_opcode(&self, _opcode: Opcode) -> bool {
+        false
+    }
+
+    /// Transform a chunk of incoming unmasked payload of a frame with header `frame_info`.
+    fn decode_payload(&mut self, _frame_info: &FrameInfo, _payload: &mut [u8]) {}
+
+    /// Transform a chunk of outgoing payload of a frame with header `frame_info`, before it gets masked.
+    fn encode_payload(&mut self, _frame_info: &FrameInfo, _payload: &mut [u8]) {}
+}
+
+/// No extensions.
+impl Extension for () {}
+
+impl<A: Extension, B: Extension> Extension for (A, B) {
+    fn reserved_bits(&self) -> u8 {
+        self.0.reserved_bits() | self.1.reserved_bits()
+    }
+
+    fn reserved_bits_for_opcode(&self, opcode: Opcode) -> u8 {
+        self.0.reserved_bits_for_opcode(opcode) | self.1.reserved_bits_for_opcode(opcode)
+    }
+
+    fn claims_opcode(&self, opcode: Opcode) -> bool {
+        self.0.claims_opcode(opcode) || self.1.claims_opcode(opcode)
+    }
+
+    fn decode_payload(&mut self, frame_info: &FrameInfo, payload: &mut [u8]) {
+        self.1.decode_payload(frame_info, payload);
+        self.0.decode_payload(frame_info, payload);
+    }
+
+    fn encode_payload(&mut self, frame_info: &FrameInfo, payload: &mut [u8]) {
+        self.0.encode_payload(frame_info, payload);
+        self.1.encode_payload(frame_info, payload);
+    }
+}
+
+impl<T: Extension + ?Sized> Extension for &mut T {
+    fn reserved_bits(&self) -> u8 {
+        (**self).reserved_bits()
+    }
+
+    fn reserved_bits_for_opcode(&self, opcode: Opcode) -> u8 {
+        (**self).reserved_bits_for_opcode(opcode)
+    }
+
+    fn claims_opcode(&self, opcode: Opcode) -> bool {
+        (**self).claims_opcode(opcode)
+    }
+
+    fn decode_payload(&mut self, frame_info: &FrameInfo, payload: &mut [u8]) {
+        (**self).decode_payload(frame_info, payload)
+    }
+
+    fn encode_payload(&mut self, frame_info: &FrameInfo, payload: &mut [u8]) {
+        (**self).encode_payload(frame_info, payload)
+    }
+}
@@ -0,0 +1,175 @@
+use super::*;
+
+extern crate std;
+
+use std::vec::Vec;
+
+use pretty_assertions::assert_eq;
+
+use crate::test_util::{decode_events, frame_with_reserved};
+
+/// Test extension XORing all payload bytes with a constant, marked by RSV3.
+struct XorExtension(u8);
+
+impl Extension for XorExtension {
+    fn reserved_bits(&self) -> u8 {
+        RSV3
+    }
+
+    fn decode_payload(&mut self, frame_info: &FrameInfo, payload: &mut [u8]) {
+        if frame_info.reserved & RSV3 != 0 {
+            payload.iter_mut().for_each(|x| *x ^= self.0);
+        }
+    }
+
+    fn encode_payload(&mut self, frame_info: &FrameInfo, payload: &mut [u8]) {
+        self.decode_payload(frame_info, payload)
+    }
+}
+
+/// Test extension defining a control opcode and shifting payload bytes.
+struct TickExtension;
+
+impl Extension for TickExtension {
+    fn claims_opcode(&self, opcode: Opcode) -> bool {
+        opcode == Opcode::ReservedControlB
+    }
+
+    fn decode_payload(&mut self, _frame_info: &FrameInfo, payload: &mut [u8]) {
+        payload.iter_mut().for_each(|x| *x = x.wrapping_sub(1));
+    }
+
+    fn encode_payload(&mut self, _frame_info: &FrameInfo, payload: &mut [u8]) {
+        payload.iter_mut().for_each(|x| *x = x.wrapping_add(1));
+    }
+}
+
+fn validate(d: &mut ValidatingFrameDecoder, data: Vec<u8>) -> Result<(), ProtocolViolation> {
+    decode_events(d, &data, usize::MAX).map(drop)
+}
+
+#[test]
+fn chaining() {
+    let mut ext = (XorExtension(0x55), TickExtension);
+    assert_eq!(ext.reserved_bits(), RSV3);
+    assert!(ext.claims_opcode(Opcode::ReservedControlB));
+    assert!(!ext.claims_opcode(Opcode::ReservedControlC));
+
+    let fi = FrameInfo {
+        reserved: RSV3,
+        ..Default::default()
+    };
+    let mut payload = *b"hello";
+    ext.encode_payload(&fi, &mut payload);
+    assert_eq!(payload, b"hello".map(|x| (x ^ 0x55).wrapping_add(1)));
+    let mut chain_ref = (&mut ext, ());
+    chain_ref.decode_payload(&fi, &mut payload);
+    assert_eq!(&payload, b"hello");
+}
+
+#[test]
+fn validator_allows_claimed_bits_and_opcodes() {
+    let mut d = ValidatingFrameDecoder::new();
+    assert_eq!(
+        validate(
+            &mut d,
+            frame_with_reserved(Opcode::Binary, RSV3, true, b"x")
+        ),
+        Err(ProtocolViolation::ReservedBits)
+    );
+
+    let mut d = ValidatingFrameDecoder::new();
+    d.allow_extension(&(XorExtension(1), TickExtension));
+    validate(
+        &mut d,
+        frame_with_reserved(Opcode::Binary, RSV3, true, b"x"),
+    )
+    .unwrap();
+    validate(
+        &mut d,
+        frame_with_reserved(Opcode::ReservedControlB, 0, true, b"tick"),
+    )
+    .unwrap();
+    validate(
+        &mut d,
+        frame_with_reserved(Opcode::Binary, RSV3, false, b"x"),
+    )
+    .unwrap();
+    validate(
+        &mut d,
+        frame_with_reserved(Opcode::Continuation, RSV3, true, b"y"),
+    )
+    .unwrap();
+    assert_eq!(
+        validate(&mut d, frame_with_reserved(Opcode::Ping, RSV3, true, b"")),
+        Err(ProtocolViolation::ReservedBits)
+    );
+    assert_eq!(
+        validate(
+            &mut d,
+            frame_with_reserved(Opcode::ReservedControlB, RSV3, true, b"")
+        ),
+        Err(ProtocolViolation::ReservedBits)
+    );
+    assert_eq!(
+        validate(
+            &mut d,
+            frame_with_reserved(Opcode::ReservedControlB, 0, false, b"")
+        ),
+        Err(ProtocolViolation::FragmentedControlFrame)
+    );
+
+    let mut d = ValidatingFrameDecoder::new();
+    d.allow_extension(&(XorExtension(1), TickExtension));
+    assert_eq!(
+        validate(
+            &mut d,
+            frame_with_reserved(Opcode::Binary, RSV1, true, b"x")
+        ),
+        Err(ProtocolViolation::ReservedBits)
+    );
+    let mut d = ValidatingFrameDecoder::new();
+    d.allow_extension(&TickExtension);
+    assert_eq!(
+        validate(
+            &mut d,
+            frame_with_reserved(Opcode::ReservedControlC, 0, true, b"")
+        ),
+        Err(ProtocolViolation::ReservedOpcode)
+    );
+}
+
+#[test]
+fn claimed_data_opcode_starts_message() {
+    struct DataOpcodeExtension;
+    impl Extension for DataOpcodeExtension {
+        fn claims_opcode(&self, opcode: Opcode) -> bool {
+            opcode == Opcode::ReservedData3
+        }
+    }
+    let mut d = ValidatingFrameDecoder::new();
+    d.allow_extension(&DataOpcodeExtension);
+    validate(
+        &mut d,
+        frame_with_reserved(Opcode::ReservedData3, 0, false, b"a"),
+    )
+    .unwrap();
+    assert_eq!(d.current_message(), Some(Opcode::ReservedData3));
+    assert_eq!(
+        validate(&mut d, frame_with_reserved(Opcode::Binary, 0, true, b"b")),
+        Err(ProtocolViolation::UnfinishedMessage)
+    );
+    let mut d = ValidatingFrameDecoder::new();
+    d.allow_extension(&DataOpcodeExtension);
+    validate(
+        &mut d,
+        frame_with_reserved(Opcode::ReservedData3, 0, false, b"a"),
+    )
+    .unwrap();
+    validate(
+        &mut d,
+        frame_with_reserved(Opcode::Continuation, 0, true, b"b"),
+    )
+    .unwrap();
+    assert_eq!(d.current_message(), None);
+}
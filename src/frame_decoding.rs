@@ -2,17 +2,65 @@ use crate::{PayloadLength, Opcode, FrameInfo, masking};
 
 use nonmax::NonMaxU8;
 
-/// When large_frames` crate feature is on (by default), any bytes can be decoded, so no error possible.
-#[cfg(feature="large_frames")]
-pub type FrameDecoderError = core::convert::Infallible;
-
-/// When large_frames` crate feature is off (like now), WebSocket frame headers denoting large frames
-/// produce this error.
-#[allow(missing_docs)]
-#[cfg(not(feature="large_frames"))]
+/// Malformed or unsupported frame header found by [`WebsocketFrameDecoder`].
+///
+/// By default the decoder is lenient and the only possible error is [`FrameDecoderError::ExceededFrameSize`]
+/// (when `large_frames` crate feature is off). Other variants are opt-in using [`FrameDecoderStrictness`].
 #[derive(Debug,PartialEq, Eq, PartialOrd, Ord,Hash,Clone, Copy)]
+#[non_exhaustive]
 pub enum FrameDecoderError {
+    /// Frame uses 64-bit payload length, but `large_frames` crate feature is off.
     ExceededFrameSize,
+    /// Payload length is not encoded in the shortest possible form,
+    /// e.g. 16-bit length below 126 or 64-bit length below 65536.
+    NonMinimalLength,
+    /// Most significant bit of 64-bit payload length is set, which is forbidden by RFC 6455 section 5.2.
+    LengthMsbSet,
+    /// Control frame payload is longer than [`crate::MAX_CONTROL_PAYLOAD_LENGTH`].
+    OversizedControlFrame,
+    /// Some of RSV1, RSV2 or RSV3 bits are set.
+    ReservedBits,
+}
+
+/// Which optional checks [`WebsocketFrameDecoder`] performs on incoming frame headers.
+///
+/// Default value is lenient: all checks are off and any header is accepted.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+pub struct FrameDecoderStrictness {
+    /// Report [`FrameDecoderError::NonMinimalLength`].
+    pub reject_non_minimal_length: bool,
+    /// Report [`FrameDecoderError::LengthMsbSet`].
+    pub reject_length_msb: bool,
+    /// Report [`FrameDecoderError::OversizedControlFrame`].
+    pub reject_oversized_control_frames: bool,
+    /// Report [`FrameDecoderError::ReservedBits`]. Should stay off when extensions using those bits are negotiated.
+    pub reject_reserved_bits: bool,
+}
+
+impl FrameDecoderStrictness {
+    /// All checks off.
+    pub const fn lenient() -> Self {
+        FrameDecoderStrictness {
+            reject_non_minimal_length: false,
+            reject_length_msb: false,
+            reject_oversized_control_frames: false,
+            reject_reserved_bits: false,
+        }
+    }
+
+    /// Reject non-canonical framing: non-minimal length encodings, 64-bit lengths with the most significant bit set
+    /// and oversized control frames.
+    ///
+    /// Reserved bits are not checked, as they may be used by negotiated extensions
+    /// (see [`crate::ValidatingFrameDecoder::allow_extension`]).
+    pub const fn strict() -> Self {
+        FrameDecoderStrictness {
+            reject_non_minimal_length: true,
+            reject_length_msb: true,
+            reject_oversized_control_frames: true,
+            reject_reserved_bits: false,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -23,12 +71,12 @@ struct SmallBufWithLen<const C: usize> {
 
 impl<const C: usize> SmallBufWithLen<C> {
     /// Take as much bytes as possible from the slice pointer, updating it in process
-    fn slurp<'a, 'c>(&'c mut self, data: &'a mut [u8]) -> &'a mut [u8] {
+    fn slurp<'a>(&mut self, data: &'a [u8]) -> &'a [u8] {
         let offset = self.len as usize;
         let maxlen = (C - offset).min(data.len());
         self.data[offset..(offset+maxlen)].copy_from_slice(&data[..maxlen]);
         self.len += maxlen as u8;
-        &mut data[maxlen..]
+        &data[maxlen..]
     }
     fn is_full(&self) -> bool {
         self.len as usize == C
@@ -75,7 +123,8 @@ impl Default for FrameDecodingState {
 /// ```
 /// 
 /// Any sequence of bytes result in a some (sensial or not) [`WebsocketFrameEvent`]
-/// sequence (exception: when `large_frames` crate feature is disabled).
+/// sequence (exceptions: when `large_frames` crate feature is disabled or when
+/// additional checks are enabled with [`WebsocketFrameDecoder::with_strictness`]).
 /// 
 /// You may want to validate it (e.g. using [`FrameInfo::is_reasonable`] method) before using.
 #[derive(Clone, Copy, Debug, Default)]
@@ -85,6 +134,7 @@ pub struct WebsocketFrameDecoder {
     basic_header: [u8; 2],
     payload_length: PayloadLength,
     original_opcode: Opcode,
+    strictness: FrameDecoderStrictness,
 }
 
 /// Return value of [`WebsocketFrameDecoder::add_data`] call.
@@ -100,6 +150,19 @@ pub struct WebsocketFrameDecoderAddDataResult {
     pub event: Option<WebsocketFrameEvent>,
 }
 
+/// Return value of [`WebsocketFrameDecoder::add_data_copy`] call.
+#[derive(Debug,Clone)]
+pub struct WebsocketFrameDecoderCopyResult {
+    /// Indicates how many bytes of input were consumed and should not be supplied again to
+    /// the subsequent invocation of [`WebsocketFrameDecoder::add_data_copy`].
+    pub consumed_bytes: usize,
+    /// Number of unmasked payload bytes written to the beginning of the output buffer.
+    /// Nonzero only for [`WebsocketFrameEvent::PayloadChunk`] events, zero for such event means the output buffer was empty.
+    pub produced_bytes: usize,
+    /// Emitted event, if any.
+    pub event: Option<WebsocketFrameEvent>,
+}
+
 #[allow(missing_docs)]
 /// Information that [`WebsocketFrameDecoder`] gives in return to bytes being fed to it.
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -132,26 +195,7 @@ pub enum WebsocketFrameEvent {
 
 impl WebsocketFrameDecoder {
     fn get_opcode(&self) -> Opcode {
-        use Opcode::*;
-        match self.basic_header[0] & 0xF {
-            0 => Continuation,
-            1 => Text,
-            2 => Binary,
-            3 => ReservedData3,
-            4 => ReservedData4,
-            5 => ReservedData5,
-            6 => ReservedData6,
-            7 => ReservedData7,
-            8 => ConnectionClose,
-            9 => Ping,
-            0xA => Pong,
-            0xB => ReservedControlB,
-            0xC => ReservedControlC,
-            0xD => ReservedControlD,
-            0xE => ReservedControlE,
-            0xF => ReservedControlF,
-            _ => unreachable!(),
-        }
+        Opcode::from_u8(self.basic_header[0] & 0xF)
     }
 
     /// Get frame info and original opcode
@@ -182,21 +226,63 @@ impl WebsocketFrameDecoder {
     /// 
     /// Input buffer needs to be mutable because it is also used to transform (unmask)
     /// payload content chunks in-place.
-    pub fn add_data<'a, 'b>(
-        &'a mut self,
-        mut data: &'b mut [u8],
+    pub fn add_data(
+        &mut self,
+        data: &mut [u8],
     ) -> Result<WebsocketFrameDecoderAddDataResult, FrameDecoderError> {
+        let (ret, phase) = self.decode(data, usize::MAX)?;
+        if let Some(phase) = phase {
+            masking::apply_mask(self.mask, &mut data[..ret.consumed_bytes], phase);
+        }
+        Ok(ret)
+    }
+
+    /// Like [`WebsocketFrameDecoder::add_data`], but reads from immutable `input` and
+    /// writes unmasked payload to `output` instead of transforming it in place.
+    ///
+    /// At most `output.len()` payload bytes are consumed by each call. Header bytes
+    /// do not need any output space. If payload data is available but `output` is empty,
+    /// an empty [`WebsocketFrameEvent::PayloadChunk`] is returned, so that it is not mistaken for a need of more input.
+    pub fn add_data_copy(
+        &mut self,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<WebsocketFrameDecoderCopyResult, FrameDecoderError> {
+        let (ret, phase) = self.decode(input, output.len())?;
+        let mut produced_bytes = 0;
+        if let Some(WebsocketFrameEvent::PayloadChunk { .. }) = ret.event {
+            produced_bytes = ret.consumed_bytes;
+            output[..produced_bytes].copy_from_slice(&input[..produced_bytes]);
+            if let Some(phase) = phase {
+                masking::apply_mask(self.mask, &mut output[..produced_bytes], phase);
+            }
+        }
+        Ok(WebsocketFrameDecoderCopyResult {
+            consumed_bytes: ret.consumed_bytes,
+            produced_bytes,
+            event: ret.event,
+        })
+    }
+
+    /// Decoding logic shared by [`WebsocketFrameDecoder::add_data`] and [`WebsocketFrameDecoder::add_data_copy`].
+    ///
+    /// Payload is not unmasked here. For `PayloadChunk` events, masking phase of the chunk's first byte is also returned.
+    fn decode(
+        &mut self,
+        mut data: &[u8],
+        max_payload_len: usize,
+    ) -> Result<(WebsocketFrameDecoderAddDataResult, Option<u8>), FrameDecoderError> {
         let original_data_len = data.len();
         loop {
             macro_rules! return_dummy {
                 () => {
-                    return Ok(WebsocketFrameDecoderAddDataResult {
+                    return Ok((WebsocketFrameDecoderAddDataResult {
                         consumed_bytes: original_data_len - data.len(),
                         event: None,
-                    });
+                    }, None));
                 };
             }
-            if data.len() == 0 && ! matches!(self.state, FrameDecodingState::PayloadData{remaining: 0, ..}) {
+            if data.is_empty() && ! matches!(self.state, FrameDecodingState::PayloadData{remaining: 0, ..}) {
                 return_dummy!();
             }
             macro_rules! try_to_fill_buffer_or_return {
@@ -214,6 +300,9 @@ impl WebsocketFrameDecoder {
                 FrameDecodingState::HeaderBeginning(ref mut v) => {
                     try_to_fill_buffer_or_return!(v);
                     self.basic_header = v;
+                    if self.strictness.reject_reserved_bits && self.basic_header[0] & 0x70 != 0 {
+                        return Err(FrameDecoderError::ReservedBits);
+                    }
                     let opcode = self.get_opcode();
                     if opcode.is_data() && opcode != Opcode::Continuation {
                         self.original_opcode = opcode;
@@ -237,13 +326,24 @@ impl WebsocketFrameDecoder {
                 }
                 FrameDecodingState::PayloadLength16(ref mut v) => {
                     try_to_fill_buffer_or_return!(v);
-                    self.payload_length = u16::from_be_bytes(v).into();
+                    let length = u16::from_be_bytes(v);
+                    if self.strictness.reject_non_minimal_length && length < 0x7E {
+                        return Err(FrameDecoderError::NonMinimalLength);
+                    }
+                    self.payload_length = length.into();
                     length_is_ready = true;
                 }
                 #[cfg(feature="large_frames")]
                 FrameDecodingState::PayloadLength64(ref mut v) => {
                     try_to_fill_buffer_or_return!(v);
-                    self.payload_length = u64::from_be_bytes(v);
+                    let length = u64::from_be_bytes(v);
+                    if self.strictness.reject_length_msb && length & (1 << 63) != 0 {
+                        return Err(FrameDecoderError::LengthMsbSet);
+                    }
+                    if self.strictness.reject_non_minimal_length && length <= 0xFFFF {
+                        return Err(FrameDecoderError::NonMinimalLength);
+                    }
+                    self.payload_length = length;
                     length_is_ready = true;
                 }
                 FrameDecodingState::MaskingKey(ref mut v) => {
@@ -254,10 +354,10 @@ impl WebsocketFrameDecoder {
                         remaining: self.payload_length,
                     };
                     let (frame_info, original_opcode) = self.get_frame_info(true);
-                    return Ok(WebsocketFrameDecoderAddDataResult {
+                    return Ok((WebsocketFrameDecoderAddDataResult {
                         consumed_bytes: original_data_len - data.len(),
                         event: Some(WebsocketFrameEvent::Start{frame_info, original_opcode}),
-                    });
+                    }, None));
                 }
                 FrameDecodingState::PayloadData {
                     phase,
@@ -268,27 +368,36 @@ impl WebsocketFrameDecoder {
                     if fi.opcode.is_data() && fi.fin {
                         self.original_opcode = Opcode::Continuation;
                     }
-                    return Ok(WebsocketFrameDecoderAddDataResult {
+                    return Ok((WebsocketFrameDecoderAddDataResult {
                         consumed_bytes: original_data_len - data.len(),
                         event: Some(WebsocketFrameEvent::End{frame_info: fi, original_opcode}
                             ),
-                    });
+                    }, None));
                 }
                 FrameDecodingState::PayloadData {
                     ref mut phase,
                     ref mut remaining,
                 } => {
                     let start_offset = original_data_len - data.len();
-                    let mut max_len = data.len();
+                    let mut max_len = data.len().min(max_payload_len);
                     if let Ok(remaining_usize) = usize::try_from(*remaining) {
                         max_len = max_len.min(remaining_usize);
                     }
-                    let (payload_chunk, _rest) = data.split_at_mut(max_len);
+                    if data.is_empty() {
+                        return_dummy!();
+                    }
+                    if max_len == 0 {
+                        // No room in the output of `add_data_copy`.
+                        let (_, original_opcode) = self.get_frame_info(false);
+                        return Ok((WebsocketFrameDecoderAddDataResult {
+                            consumed_bytes: 0,
+                            event: Some(WebsocketFrameEvent::PayloadChunk{original_opcode}),
+                        }, None));
+                    }
 
+                    let chunk_phase = phase.map(|x| x.get());
                     if let Some(phase) = phase {
-                        let mut ph = phase.get();
-                        masking::apply_mask(self.mask, payload_chunk, ph);
-                        ph += payload_chunk.len() as u8;
+                        let ph = phase.get() + (max_len % 4) as u8;
                         *phase = NonMaxU8::new(ph & 0x03).unwrap();
                     }
 
@@ -298,13 +407,19 @@ impl WebsocketFrameDecoder {
                         original_opcode = self.original_opcode;
                     }
                     assert_eq!(start_offset, 0);
-                    return Ok(WebsocketFrameDecoderAddDataResult {
+                    return Ok((WebsocketFrameDecoderAddDataResult {
                         consumed_bytes: max_len,
                         event: Some(WebsocketFrameEvent::PayloadChunk{original_opcode}),
-                    });
+                    }, chunk_phase));
                 }
             }
             if length_is_ready {
+                if self.strictness.reject_oversized_control_frames
+                    && self.get_opcode().is_control()
+                    && self.payload_length > crate::MAX_CONTROL_PAYLOAD_LENGTH.into()
+                {
+                    return Err(FrameDecoderError::OversizedControlFrame);
+                }
                 if self.basic_header[1] & 0x80 == 0x80 {
                     self.state = FrameDecodingState::MaskingKey(SmallBufWithLen::new());
                 } else {
@@ -313,15 +428,71 @@ impl WebsocketFrameDecoder {
                         remaining: self.payload_length,
                     };
                     let (frame_info, original_opcode) = self.get_frame_info(false);
-                    return Ok(WebsocketFrameDecoderAddDataResult {
+                    return Ok((WebsocketFrameDecoderAddDataResult {
                         consumed_bytes: original_data_len - data.len(),
                         event: Some(WebsocketFrameEvent::Start{frame_info, original_opcode}),
-                    });
+                    }, None));
                 }
             }
         }
     }
 
+    /// Undo consumption of last `n_bytes` payload bytes of the current frame, so they can be supplied again later.
+    ///
+    /// Use it for backpressure when the application cannot accept the whole [`WebsocketFrameEvent::PayloadChunk`] yet.
+    /// This variant is for [`WebsocketFrameDecoder::add_data_copy`], which leaves input intact.
+    /// After [`WebsocketFrameDecoder::add_data`] use [`WebsocketFrameDecoder::unconsume_payload_in_place`] instead.
+    ///
+    /// Returns `false` and does nothing if the decoder is not in the middle of (or at the end of, before
+    /// [`WebsocketFrameEvent::End`] is emitted) frame payload, or if fewer than `n_bytes` of it were consumed.
+    pub fn unconsume_payload(&mut self, n_bytes: usize) -> bool {
+        let FrameDecodingState::PayloadData {
+            ref mut phase,
+            ref mut remaining,
+        } = self.state
+        else {
+            return false;
+        };
+        let consumed = self.payload_length - *remaining;
+        match PayloadLength::try_from(n_bytes) {
+            Ok(n) if n <= consumed => *remaining += n,
+            _ => return false,
+        }
+        if let Some(phase) = phase {
+            let modulo = (n_bytes % 4) as u8;
+            *phase = NonMaxU8::new((phase.get() + 4 - modulo) % 4).unwrap();
+        }
+        true
+    }
+
+    /// Like [`WebsocketFrameDecoder::unconsume_payload`], but for bytes unmasked in place by [`WebsocketFrameDecoder::add_data`].
+    ///
+    /// `data` should be the unconsumed trailing part of the last payload chunk. It is masked back,
+    /// so that it can be supplied to `add_data` again.
+    pub fn unconsume_payload_in_place(&mut self, data: &mut [u8]) -> bool {
+        if !self.unconsume_payload(data.len()) {
+            return false;
+        }
+        if let FrameDecodingState::PayloadData {
+            phase: Some(phase), ..
+        } = self.state
+        {
+            masking::apply_mask(self.mask, data, phase.get());
+        }
+        true
+    }
+
+    /// Iterate over events produced from `data`, along with payload bytes of each event.
+    ///
+    /// This performs the `add_data` calling loop for you, including the final call with an empty buffer.
+    pub fn events<'a>(&mut self, data: &'a mut [u8]) -> WebsocketFrameEvents<'_, 'a> {
+        WebsocketFrameEvents {
+            decoder: self,
+            rest: data,
+            failed: false,
+        }
+    }
+
     /// There is no incomplete WebSocket frame at this moment and EOF is valid here.
     ///
     /// This method is not related to [`Opcode::ConnectionClose`] in any way.
@@ -339,6 +510,96 @@ impl WebsocketFrameDecoder {
             basic_header: [0; 2],
             payload_length: 0,
             original_opcode: Opcode::Continuation,
+            strictness: FrameDecoderStrictness::lenient(),
+        }
+    }
+
+    /// Create new instance which rejects non-canonical framing, see [`FrameDecoderStrictness::strict`].
+    #[inline]
+    pub const fn new_strict() -> Self {
+        WebsocketFrameDecoder::with_strictness(FrameDecoderStrictness::strict())
+    }
+
+    /// Create new instance performing given optional checks of frame headers.
+    #[inline]
+    pub const fn with_strictness(strictness: FrameDecoderStrictness) -> Self {
+        let mut d = WebsocketFrameDecoder::new();
+        d.strictness = strictness;
+        d
+    }
+
+    /// Change which optional checks of frame headers are performed.
+    #[inline]
+    pub fn set_strictness(&mut self, strictness: FrameDecoderStrictness) {
+        self.strictness = strictness;
+    }
+
+    /// Currently active optional checks.
+    #[inline]
+    pub const fn strictness(&self) -> FrameDecoderStrictness {
+        self.strictness
+    }
+}
+
+/// Iterator over events decoded from a buffer, returned by [`WebsocketFrameDecoder::events`].
+///
+/// Each item is an event with unmasked payload bytes belonging to it (empty unless the event is
+/// [`WebsocketFrameEvent::PayloadChunk`]). Iteration ends when the buffer is exhausted and no more
+/// events are pending; incomplete frame data is kept in the decoder for the next buffer.
+///
+/// ```
+/// use websocket_sans_io::{WebsocketFrameDecoder, WebsocketFrameEvent};
+///
+/// let mut decoder = WebsocketFrameDecoder::new();
+/// let mut data = *b"\x81\x85\x37\xfa\x21\x3d\x7f\x9f\x4d\x51\x58";
+/// let mut text = Vec::new();
+/// for event in decoder.events(&mut data) {
+///     let (event, payload) = event.unwrap();
+///     if let WebsocketFrameEvent::PayloadChunk { .. } = event {
+///         text.extend_from_slice(payload);
+///     }
+/// }
+/// assert_eq!(text, b"Hello");
+/// ```
+#[derive(Debug)]
+pub struct WebsocketFrameEvents<'d, 'a> {
+    decoder: &'d mut WebsocketFrameDecoder,
+    rest: &'a mut [u8],
+    failed: bool,
+}
+
+impl<'d, 'a> WebsocketFrameEvents<'d, 'a> {
+    /// Stop iterating and get the part of the buffer not yet supplied to the decoder.
+    #[inline]
+    pub fn into_rest(self) -> &'a mut [u8] {
+        self.rest
+    }
+}
+
+impl<'d, 'a> Iterator for WebsocketFrameEvents<'d, 'a> {
+    type Item = Result<(WebsocketFrameEvent, &'a mut [u8]), FrameDecoderError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        loop {
+            let buf = core::mem::take(&mut self.rest);
+            let ret = match self.decoder.add_data(buf) {
+                Ok(x) => x,
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            };
+            let (chunk, rest) = buf.split_at_mut(ret.consumed_bytes);
+            self.rest = rest;
+            match ret.event {
+                Some(ev @ WebsocketFrameEvent::PayloadChunk { .. }) => return Some(Ok((ev, chunk))),
+                Some(ev) => return Some(Ok((ev, &mut []))),
+                None if ret.consumed_bytes == 0 => return None,
+                None => (),
+            }
         }
     }
 }
@@ -1,7 +1,14 @@
 use nonmax::NonMaxU8;
 use tinyvec::ArrayVec;
 
-use crate::{FrameInfo, MAX_HEADER_LENGTH};
+use crate::{FrameInfo, MaskingKeySource, PayloadLength, Role, MAX_HEADER_LENGTH};
+
+/// Error from [`WebsocketFrameEncoder::start_frame`] of an encoder with a role.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum FrameEncoderError {
+    /// Frame is unmasked, but the encoder is a [`Role::Client`], or masked, but the encoder is a [`Role::Server`].
+    MaskingMismatch,
+}
 
 /// A low-level WebSocket frames decoder.
 /// 
@@ -18,6 +25,7 @@ use crate::{FrameInfo, MAX_HEADER_LENGTH};
 pub struct WebsocketFrameEncoder {
     mask: [u8; 4],
     phase: Option<NonMaxU8>,
+    role: Option<Role>,
 }
 
 impl WebsocketFrameEncoder {
@@ -26,9 +34,28 @@ impl WebsocketFrameEncoder {
         WebsocketFrameEncoder {
             mask: [0; 4],
             phase: None,
+            role: None,
         }
     }
 
+    /// Create new instance of WebsocketFrameEncoder which knows which side of the connection it is on.
+    ///
+    /// [`WebsocketFrameEncoder::start_frame_with_key_source`] masks frames according to the role,
+    /// [`WebsocketFrameEncoder::start_frame`] rejects frames contradicting it.
+    pub const fn with_role(role: Role) -> WebsocketFrameEncoder {
+        WebsocketFrameEncoder {
+            mask: [0; 4],
+            phase: None,
+            role: Some(role),
+        }
+    }
+
+    /// Role this encoder was created with, if any.
+    #[inline]
+    pub const fn role(&self) -> Option<Role> {
+        self.role
+    }
+
     /// Serialize given frame header as bytes. You should write all those bytes to the socket 
     /// before starting to write payload contant (if any).
     /// 
@@ -41,6 +68,10 @@ impl WebsocketFrameEncoder {
     /// or getting next frame header when current frame's payload is not completely written.
     /// 
     /// Use masked frames when you are client and unmasked frames when you are server.
+    /// See also [`WebsocketFrameEncoder::start_frame_with_key_source`].
+    /// If the encoder has a role, `frame_info.mask` is not overridden here, but
+    /// a `frame_info.mask` contradicting the role is rejected with [`FrameEncoderError::MaskingMismatch`].
+    /// Encoders without a role never fail.
     /// 
     /// Writing frame header with nonzero `frame_info.payload_length` means you are obligated to
     /// write this number of bytes before writing any new frame.
@@ -50,7 +81,20 @@ impl WebsocketFrameEncoder {
     /// This also allows to interrupt the data transmissing to send a [`crate::Opcode::Ping`]
     /// or reply with a [`crate::Opcode::Pong`].
     #[inline]
-    pub fn start_frame(&mut self, frame_info: &FrameInfo) -> ArrayVec<[u8; MAX_HEADER_LENGTH]> {
+    pub fn start_frame(
+        &mut self,
+        frame_info: &FrameInfo,
+    ) -> Result<ArrayVec<[u8; MAX_HEADER_LENGTH]>, FrameEncoderError> {
+        if matches!(
+            (self.role, frame_info.mask),
+            (Some(Role::Client), None) | (Some(Role::Server), Some(_))
+        ) {
+            return Err(FrameEncoderError::MaskingMismatch);
+        }
+        Ok(self.start_frame_unchecked(frame_info))
+    }
+
+    fn start_frame_unchecked(&mut self, frame_info: &FrameInfo) -> ArrayVec<[u8; MAX_HEADER_LENGTH]> {
         if let Some(m) = frame_info.mask {
             self.mask = m;
             self.phase = Some(NonMaxU8::default());
@@ -60,6 +104,26 @@ impl WebsocketFrameEncoder {
         encode_frame_header(frame_info)
     }
 
+    /// Like [`WebsocketFrameEncoder::start_frame`], but overrides `frame_info.mask` according to the role:
+    /// [`Role::Client`] masks the frame with a key obtained from `key_source`,
+    /// [`Role::Server`] leaves the frame unmasked.
+    ///
+    /// If the encoder has no role, `frame_info.mask` is used as is and `key_source` is not invoked.
+    #[inline]
+    pub fn start_frame_with_key_source(
+        &mut self,
+        frame_info: &FrameInfo,
+        key_source: &mut impl MaskingKeySource,
+    ) -> ArrayVec<[u8; MAX_HEADER_LENGTH]> {
+        let mut frame_info = *frame_info;
+        match self.role {
+            Some(Role::Client) => frame_info.mask = Some(key_source.next_masking_key()),
+            Some(Role::Server) => frame_info.mask = None,
+            None => (),
+        }
+        self.start_frame_unchecked(&frame_info)
+    }
+
     /// Prepare this memory chunk to be transfitted to the socket as a part of WebSocket frame payload.
     /// 
     /// Call this after `start_frame`.
@@ -103,6 +167,123 @@ impl WebsocketFrameEncoder {
     }
 }
 
+/// Result of [`WebsocketFrameWriter::write`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WebsocketFrameWriterResult {
+    /// Number of payload bytes taken from the input buffer.
+    pub consumed_bytes: usize,
+    /// Number of bytes (header and masked payload) written to the output buffer.
+    pub produced_bytes: usize,
+}
+
+/// A wrapper around [`WebsocketFrameEncoder`] which copies frame header and payload
+/// from immutable input into a separate output buffer, masking it on the way.
+///
+/// Unlike with [`WebsocketFrameEncoder::transform_frame_payload`], payload does not need
+/// to be in a mutable buffer, so there is no need for a scratch copy of e.g. static strings.
+/// Output buffer may be of any size; header and payload are written progressively across
+/// multiple [`WebsocketFrameWriter::write`] calls.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct WebsocketFrameWriter {
+    encoder: WebsocketFrameEncoder,
+    header: [u8; MAX_HEADER_LENGTH],
+    header_len: u8,
+    header_offset: u8,
+    remaining: PayloadLength,
+}
+
+impl WebsocketFrameWriter {
+    /// Create new instance.
+    pub const fn new() -> WebsocketFrameWriter {
+        WebsocketFrameWriter::from_encoder(WebsocketFrameEncoder::new())
+    }
+
+    /// Create new instance which knows which side of the connection it is on.
+    ///
+    /// Role is handled the same way as by [`WebsocketFrameEncoder::with_role`].
+    pub const fn with_role(role: Role) -> WebsocketFrameWriter {
+        WebsocketFrameWriter::from_encoder(WebsocketFrameEncoder::with_role(role))
+    }
+
+    const fn from_encoder(encoder: WebsocketFrameEncoder) -> WebsocketFrameWriter {
+        WebsocketFrameWriter {
+            encoder,
+            header: [0; MAX_HEADER_LENGTH],
+            header_len: 0,
+            header_offset: 0,
+            remaining: 0,
+        }
+    }
+
+    /// Begin a new frame. Its header will be emitted by subsequent [`WebsocketFrameWriter::write`] calls,
+    /// followed by exactly `frame_info.payload_length` bytes of payload.
+    ///
+    /// Same caveats as for [`WebsocketFrameEncoder::start_frame`] apply. Previous frame should be
+    /// finished (see [`WebsocketFrameWriter::frame_finished`]), otherwise its unwritten part is lost.
+    pub fn start_frame(&mut self, frame_info: &FrameInfo) -> Result<(), FrameEncoderError> {
+        let header = self.encoder.start_frame(frame_info)?;
+        self.set_header(&header, frame_info.payload_length);
+        Ok(())
+    }
+
+    /// Like [`WebsocketFrameWriter::start_frame`], but sets masking according to the role,
+    /// see [`WebsocketFrameEncoder::start_frame_with_key_source`].
+    pub fn start_frame_with_key_source(
+        &mut self,
+        frame_info: &FrameInfo,
+        key_source: &mut impl MaskingKeySource,
+    ) {
+        let header = self.encoder.start_frame_with_key_source(frame_info, key_source);
+        self.set_header(&header, frame_info.payload_length);
+    }
+
+    fn set_header(&mut self, header: &[u8], payload_length: PayloadLength) {
+        self.header[..header.len()].copy_from_slice(header);
+        self.header_len = header.len() as u8;
+        self.header_offset = 0;
+        self.remaining = payload_length;
+    }
+
+    /// Write pending part of the header, then copy and mask as much of `input` as fits into `output`.
+    ///
+    /// Payload bytes beyond the end of current frame are not consumed.
+    pub fn write(&mut self, input: &[u8], output: &mut [u8]) -> WebsocketFrameWriterResult {
+        let header = &self.header[(self.header_offset as usize)..(self.header_len as usize)];
+        let n_header = header.len().min(output.len());
+        output[..n_header].copy_from_slice(&header[..n_header]);
+        self.header_offset += n_header as u8;
+
+        let output = &mut output[n_header..];
+        let mut n = input.len().min(output.len());
+        #[allow(irrefutable_let_patterns, clippy::unnecessary_fallible_conversions)] // `PayloadLength` is `u16` without `large_frames`
+        if let Ok(remaining) = usize::try_from(self.remaining) {
+            n = n.min(remaining);
+        }
+        if self.header_offset < self.header_len {
+            n = 0;
+        }
+        output[..n].copy_from_slice(&input[..n]);
+        self.encoder.transform_frame_payload(&mut output[..n]);
+        self.remaining -= n as PayloadLength;
+        WebsocketFrameWriterResult {
+            consumed_bytes: n,
+            produced_bytes: n_header + n,
+        }
+    }
+
+    /// Header and all payload of current frame have been written.
+    #[inline]
+    pub const fn frame_finished(&self) -> bool {
+        self.header_offset == self.header_len && self.remaining == 0
+    }
+
+    /// Number of payload bytes still to be written for the current frame.
+    #[inline]
+    pub const fn remaining_payload(&self) -> PayloadLength {
+        self.remaining
+    }
+}
+
 /// Just encode the header to bytes without using any encoder instance.
 /// 
 /// May be useful when you do not need masking.
@@ -137,7 +318,7 @@ pub fn encode_frame_header(frame_info: &FrameInfo) -> ArrayVec<[u8; MAX_HEADER_L
         x => {
             second_byte |= 0x7F;
             ret.push(second_byte);
-            ret.extend((x as u64).to_be_bytes());
+            ret.extend(x.to_be_bytes());
         }
         #[cfg(not(feature = "large_frames"))]
         _ => unreachable!(),
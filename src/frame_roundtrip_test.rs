@@ -36,7 +36,7 @@ fn roundtrip_frames(mut input: Vec<u8>) -> Result<Vec<u8>, TestCaseError> {
             #[allow(unused_assignments)]
             Some(WebsocketFrameEvent::Start{frame_info:info, original_opcode:_}) => {
                 cached_info = info;
-                result.extend(encoder.start_frame(&info));
+                result.extend(encoder.start_frame(&info).unwrap());
             }
             Some(WebsocketFrameEvent::PayloadChunk { original_opcode: for_opcode }) => {
                 if cached_info.opcode != Opcode::Continuation {
@@ -78,7 +78,7 @@ proptest! {
 }
 
 fn byte_blob() -> impl Strategy<Value = ByteBlob> {
-    vec(any::<u8>(), 50..80000).prop_map(|x|ByteBlob(x))
+    vec(any::<u8>(), 50..80000).prop_map(ByteBlob)
 }
 
 
@@ -0,0 +1,115 @@
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use std::io;
+
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_sink::Sink;
+
+use crate::async_stream::{AsyncTransport, AsyncWebSocket};
+use crate::{ClosePayload, DecoderLimits, Role, WebsocketMessage};
+
+#[derive(Debug)]
+struct FuturesIo<S>(S);
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncTransport for FuturesIo<S> {
+    fn poll_read(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+
+    fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_close(cx)
+    }
+}
+
+/// A WebSocket connection over runtime-agnostic `futures-io` [`AsyncRead`] + [`AsyncWrite`]
+/// (e.g. async-std or smol `TcpStream`), after the handshake is done.
+///
+/// Requires `futures_io` crate feature.
+///
+/// Works like [`crate::TokioWebSocketStream`]: it is a [`Stream`] of incoming data messages and a [`Sink`] of outgoing messages.
+/// Short writes of the transport are resumed from the first unaccepted byte of the buffered (already masked) frame.
+///
+/// Wrapped stream should be `Unpin`; use `Box::pin` for other streams.
+#[derive(Debug)]
+pub struct FuturesWebSocketStream<S> {
+    inner: AsyncWebSocket<FuturesIo<S>>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> FuturesWebSocketStream<S> {
+    /// Wrap a stream, which already passed the WebSocket handshake.
+    pub fn new(inner: S, role: Role) -> Self {
+        FuturesWebSocketStream::with_initial_data(inner, role, &[])
+    }
+
+    /// Wrap a stream, which already passed the WebSocket handshake, also supplying bytes
+    /// which were read from it after the handshake.
+    pub fn with_initial_data(inner: S, role: Role, data: &[u8]) -> Self {
+        FuturesWebSocketStream {
+            inner: AsyncWebSocket::new(FuturesIo(inner), role, data),
+        }
+    }
+
+    /// Payload of the Close frame received from peer, if any.
+    pub fn peer_close(&self) -> Option<ClosePayload<'_>> {
+        self.inner.peer_close()
+    }
+
+    /// Set resource limits for incoming frames, which are unlimited by default.
+    /// Exceeding them fails the connection with [`crate::CloseCode::MessageTooBig`].
+    pub fn set_limits(&mut self, limits: DecoderLimits) {
+        self.inner.set_limits(limits)
+    }
+
+    /// Get reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.inner.inner.0
+    }
+
+    /// Get mutable reference to the underlying stream. Reading or writing it directly would likely corrupt the WebSocket connection.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner.inner.0
+    }
+
+    /// Unwrap the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.inner.inner.0
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Stream for FuturesWebSocketStream<S> {
+    type Item = io::Result<WebsocketMessage>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().inner.poll_next_message(cx)
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Sink<WebsocketMessage> for FuturesWebSocketStream<S> {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().inner.poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: WebsocketMessage) -> io::Result<()> {
+        self.get_mut().inner.start_send(item.opcode, &item.payload)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().inner.poll_close(cx)
+    }
+}
@@ -0,0 +1,145 @@
+use super::*;
+
+extern crate std;
+
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::io;
+use std::vec::Vec;
+
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_sink::Sink;
+use pretty_assertions::assert_eq;
+
+use crate::test_util::{close_payload, frame, frames};
+use crate::{CloseCode, Opcode};
+
+/// Transport which is not ready on every other call and transfers at most 3 bytes at once.
+struct Stingy {
+    input: Vec<u8>,
+    position: usize,
+    output: Vec<u8>,
+    ready: bool,
+}
+
+impl Stingy {
+    fn poll_turn(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        self.ready = !self.ready;
+        if self.ready {
+            Poll::Ready(())
+        } else {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+impl AsyncRead for Stingy {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        core::task::ready!(this.poll_turn(cx));
+        let n = buf.len().min(3).min(this.input.len() - this.position);
+        buf[..n].copy_from_slice(&this.input[this.position..(this.position + n)]);
+        this.position += n;
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl AsyncWrite for Stingy {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        core::task::ready!(this.poll_turn(cx));
+        let n = buf.len().min(3);
+        this.output.extend_from_slice(&buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Poll until ready. [`Stingy`] wakes immediately, so busy-polling is enough.
+fn poll_until<T>(mut f: impl FnMut(&mut Context<'_>) -> Poll<T>) -> T {
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(x) = f(&mut cx) {
+            return x;
+        }
+    }
+}
+
+fn send(ws: &mut FuturesWebSocketStream<Stingy>, opcode: Opcode, payload: &[u8]) {
+    poll_until(|cx| Pin::new(&mut *ws).poll_ready(cx)).unwrap();
+    Pin::new(&mut *ws)
+        .start_send(WebsocketMessage {
+            opcode,
+            payload: payload.to_vec(),
+        })
+        .unwrap();
+}
+
+#[test]
+fn futures_client_partial_writes() {
+    let input = [
+        frame(Opcode::Ping, true, None, b"ping"),
+        frame(Opcode::Text, true, None, b"hello"),
+        frame(
+            Opcode::ConnectionClose,
+            true,
+            None,
+            &close_payload(CloseCode::Normal),
+        ),
+    ]
+    .concat();
+    let mut ws = FuturesWebSocketStream::new(
+        Stingy {
+            input,
+            position: 0,
+            output: Vec::new(),
+            ready: false,
+        },
+        Role::Client,
+    );
+    let long: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    send(&mut ws, Opcode::Binary, &long);
+    send(&mut ws, Opcode::Text, b"odd");
+    poll_until(|cx| Pin::new(&mut ws).poll_flush(cx)).unwrap();
+
+    let msg = poll_until(|cx| Pin::new(&mut ws).poll_next(cx))
+        .unwrap()
+        .unwrap();
+    assert_eq!(msg.opcode, Opcode::Text);
+    assert_eq!(msg.payload, b"hello");
+    assert!(poll_until(|cx| Pin::new(&mut ws).poll_next(cx)).is_none());
+    assert_eq!(ws.peer_close().unwrap().code, Some(CloseCode::Normal));
+    poll_until(|cx| Pin::new(&mut ws).poll_close(cx)).unwrap();
+
+    assert_eq!(
+        frames(ws.into_inner().output),
+        std::vec![
+            (Opcode::Binary, true, true, long),
+            (Opcode::Text, true, true, b"odd".to_vec()),
+            (Opcode::Pong, true, true, b"ping".to_vec()),
+            (
+                Opcode::ConnectionClose,
+                true,
+                true,
+                close_payload(CloseCode::Normal)
+            ),
+        ]
+    );
+}
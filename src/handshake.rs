@@ -0,0 +1,444 @@
+use crate::{compute_accept_key, verify_accept_key};
+
+/// Error from HTTP/1.1 Upgrade handshake parsing or generation.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum HandshakeError {
+    /// Output buffer is too small to hold the request or response.
+    BufferTooSmall,
+    /// Header value to be written contains CR or LF characters.
+    InvalidHeaderValue,
+    /// Request or response is not a well-formed HTTP/1.1 message head.
+    Malformed,
+    /// HTTP message head is not complete within the maximum allowed length.
+    HeadTooLong,
+    /// Header which must not appear more than once (e.g. `Sec-WebSocket-Extensions` in a response) is repeated.
+    RepeatedHeader,
+    /// Request method is not `GET`.
+    NotGet,
+    /// HTTP version is not 1.1.
+    UnsupportedHttpVersion,
+    /// Server replied with a status code other than 101.
+    UnexpectedStatus(u16),
+    /// `Upgrade: websocket` header is missing.
+    MissingUpgrade,
+    /// `Connection: Upgrade` header is missing.
+    MissingConnectionUpgrade,
+    /// `Sec-WebSocket-Version` is not 13.
+    UnsupportedWebsocketVersion,
+    /// `Sec-WebSocket-Key` header is missing or malformed.
+    InvalidKey,
+    /// `Sec-WebSocket-Accept` header is missing, malformed or does not match the key.
+    InvalidAccept,
+    /// `Sec-WebSocket-Protocol` or `Sec-WebSocket-Extensions` header is malformed.
+    InvalidNegotiationHeader,
+    /// Server selected a subprotocol which client has not offered.
+    UnexpectedProtocol,
+    /// Server accepted an extension which client has not offered, or accepted it more than once.
+    UnexpectedExtension,
+}
+
+/// Maximum length of HTTP message head accepted by [`ParsedRequest::parse`] and [`ParsedResponse::parse`].
+pub const DEFAULT_MAX_HEAD_LENGTH: usize = 8192;
+
+/// Successfully parsed HTTP message head.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Parsed<T> {
+    /// Parsed content.
+    pub value: T,
+    /// Length of the HTTP message head, including the final empty line.
+    ///
+    /// Bytes after that are already WebSocket frames and should be supplied
+    /// to [`crate::WebsocketFrameDecoder::add_data`].
+    pub consumed_bytes: usize,
+}
+
+/// Raw headers of a HTTP message, as found in the received buffer.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct HttpHeaders<'a> {
+    block: &'a str,
+}
+
+impl<'a> HttpHeaders<'a> {
+    /// Iterate all headers as `(name, value)` pairs, with whitespace around value trimmed.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a str)> + Clone + 'a {
+        self.block
+            .split("\r\n")
+            .filter(|line| !line.is_empty())
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name, value.trim_matches(|c| c == ' ' || c == '\t')))
+    }
+
+    /// Get value of the first header with the given name (case-insensitive).
+    pub fn get(&self, name: &str) -> Option<&'a str> {
+        self.get_all(name).next()
+    }
+
+    /// Iterate values of all headers with the given name (case-insensitive), in order of appearance.
+    pub fn get_all<'n>(&self, name: &'n str) -> impl Iterator<Item = &'a str> + Clone + 'n
+    where
+        'a: 'n,
+    {
+        self.iter()
+            .filter(move |(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    /// Get value of a header which must not appear more than once.
+    fn get_unique(&self, name: &str) -> Result<Option<&'a str>, HandshakeError> {
+        let mut values = self.get_all(name);
+        let ret = values.next();
+        if values.next().is_some() {
+            return Err(HandshakeError::RepeatedHeader);
+        }
+        Ok(ret)
+    }
+
+    /// Check if comma-separated header with the given name contains a token (case-insensitive),
+    /// considering all occurrences of the header.
+    pub fn has_token(&self, name: &str, token: &str) -> bool {
+        self.iter()
+            .filter(|(n, _)| n.eq_ignore_ascii_case(name))
+            .flat_map(|(_, v)| v.split(','))
+            .any(|t| t.trim().eq_ignore_ascii_case(token))
+    }
+}
+
+/// All occurrences of a header which may be repeated, e.g. `Sec-WebSocket-Extensions` in a request.
+///
+/// As per RFC 7230, they are equivalent to one header with all values joined by commas.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct HeaderValues<'a> {
+    headers: HttpHeaders<'a>,
+    name: &'static str,
+}
+
+impl<'a> HeaderValues<'a> {
+    /// Iterate values of each occurrence of the header.
+    pub fn iter(&self) -> impl Iterator<Item = &'a str> + Clone + 'a {
+        self.headers.get_all(self.name)
+    }
+
+    /// Whether the header is absent.
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+/// Find the end of HTTP message head and split it into start line and headers.
+///
+/// Fails if the head (including the final empty line) does not fit in `max_head_length` bytes.
+fn split_head(
+    buf: &[u8],
+    max_head_length: usize,
+) -> Result<Option<(&str, HttpHeaders<'_>, usize)>, HandshakeError> {
+    let limited = &buf[..buf.len().min(max_head_length)];
+    let Some(pos) = limited.windows(4).position(|w| w == b"\r\n\r\n") else {
+        if buf.len() >= max_head_length {
+            return Err(HandshakeError::HeadTooLong);
+        }
+        return Ok(None);
+    };
+    let consumed_bytes = pos + 4;
+    let head = core::str::from_utf8(&buf[..pos]).map_err(|_| HandshakeError::Malformed)?;
+    let (start_line, block) = head.split_once("\r\n").unwrap_or((head, ""));
+    if !block.is_empty() && block.split("\r\n").any(|line| !line.contains(':')) {
+        return Err(HandshakeError::Malformed);
+    }
+    Ok(Some((start_line, HttpHeaders { block }, consumed_bytes)))
+}
+
+fn check_upgrade_headers(headers: &HttpHeaders) -> Result<(), HandshakeError> {
+    if !headers.has_token("Upgrade", "websocket") {
+        return Err(HandshakeError::MissingUpgrade);
+    }
+    if !headers.has_token("Connection", "Upgrade") {
+        return Err(HandshakeError::MissingConnectionUpgrade);
+    }
+    Ok(())
+}
+
+fn is_base64(s: &[u8]) -> bool {
+    s.iter()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, b'+' | b'/' | b'='))
+}
+
+/// Helper for writing HTTP message heads into a fixed buffer.
+pub(crate) struct HeadWriter<'a> {
+    pub(crate) buf: &'a mut [u8],
+    pub(crate) len: usize,
+}
+
+impl<'a> core::fmt::Write for HeadWriter<'a> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.put(s.as_bytes()).map_err(|_| core::fmt::Error)
+    }
+}
+
+impl<'a> HeadWriter<'a> {
+    pub(crate) fn put(&mut self, s: &[u8]) -> Result<(), HandshakeError> {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(HandshakeError::BufferTooSmall);
+        }
+        self.buf[self.len..end].copy_from_slice(s);
+        self.len = end;
+        Ok(())
+    }
+
+    fn header(&mut self, name: &str, value: &[u8]) -> Result<(), HandshakeError> {
+        if value.iter().any(|c| *c == b'\r' || *c == b'\n')
+            || name.bytes().any(|c| c == b'\r' || c == b'\n' || c == b':')
+        {
+            return Err(HandshakeError::InvalidHeaderValue);
+        }
+        self.put(name.as_bytes())?;
+        self.put(b": ")?;
+        self.put(value)?;
+        self.put(b"\r\n")
+    }
+
+    fn optional_header(&mut self, name: &str, value: Option<&str>) -> Result<(), HandshakeError> {
+        match value {
+            Some(v) => self.header(name, v.as_bytes()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Client's opening handshake request, to be written with [`ClientRequest::write`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ClientRequest<'a> {
+    /// Request target, e.g. `/chat`.
+    pub path: &'a str,
+    /// Value for the `Host` header.
+    pub host: &'a str,
+    /// Base64-encoded 16 random bytes for the `Sec-WebSocket-Key` header.
+    pub key: [u8; 24],
+    /// Value for the `Origin` header, if any.
+    pub origin: Option<&'a str>,
+    /// Value for the `Sec-WebSocket-Protocol` header, if any.
+    pub protocols: Option<&'a str>,
+    /// Value for the `Sec-WebSocket-Extensions` header, if any.
+    pub extensions: Option<&'a str>,
+    /// Additional headers, e.g. for authentication.
+    pub extra_headers: &'a [(&'a str, &'a str)],
+}
+
+impl<'a> ClientRequest<'a> {
+    /// Write the request into `out` buffer, returning number of bytes written.
+    pub fn write(&self, out: &mut [u8]) -> Result<usize, HandshakeError> {
+        if self.path.bytes().any(|c| c == b' ' || c == b'\r' || c == b'\n') {
+            return Err(HandshakeError::InvalidHeaderValue);
+        }
+        let mut w = HeadWriter { buf: out, len: 0 };
+        w.put(b"GET ")?;
+        w.put(self.path.as_bytes())?;
+        w.put(b" HTTP/1.1\r\n")?;
+        w.header("Host", self.host.as_bytes())?;
+        w.header("Upgrade", b"websocket")?;
+        w.header("Connection", b"Upgrade")?;
+        w.header("Sec-WebSocket-Key", &self.key)?;
+        w.header("Sec-WebSocket-Version", b"13")?;
+        w.optional_header("Origin", self.origin)?;
+        w.optional_header("Sec-WebSocket-Protocol", self.protocols)?;
+        w.optional_header("Sec-WebSocket-Extensions", self.extensions)?;
+        for (name, value) in self.extra_headers {
+            w.header(name, value.as_bytes())?;
+        }
+        w.put(b"\r\n")?;
+        Ok(w.len)
+    }
+}
+
+/// Client's opening handshake request, as parsed by the server using [`ParsedRequest::parse`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ParsedRequest<'a> {
+    /// Request target, e.g. `/chat`.
+    pub path: &'a str,
+    /// Value of the `Host` header.
+    pub host: Option<&'a str>,
+    /// Value of the `Sec-WebSocket-Key` header.
+    pub key: [u8; 24],
+    /// Value of the `Origin` header.
+    pub origin: Option<&'a str>,
+    /// Values of all `Sec-WebSocket-Protocol` headers.
+    pub protocols: HeaderValues<'a>,
+    /// Values of all `Sec-WebSocket-Extensions` headers.
+    pub extensions: HeaderValues<'a>,
+    /// All headers of the request.
+    pub headers: HttpHeaders<'a>,
+}
+
+impl<'a> ParsedRequest<'a> {
+    /// Try to parse a request from bytes received so far.
+    ///
+    /// Returns `Ok(None)` if the request head is not complete yet. In that case,
+    /// read more bytes from the socket, append them to the buffer and try again.
+    ///
+    /// Request head longer than [`DEFAULT_MAX_HEAD_LENGTH`] is rejected with [`HandshakeError::HeadTooLong`].
+    pub fn parse(buf: &'a [u8]) -> Result<Option<Parsed<ParsedRequest<'a>>>, HandshakeError> {
+        ParsedRequest::parse_with_max_head_length(buf, DEFAULT_MAX_HEAD_LENGTH)
+    }
+
+    /// Like [`ParsedRequest::parse`], but with a custom limit on the request head length.
+    pub fn parse_with_max_head_length(
+        buf: &'a [u8],
+        max_head_length: usize,
+    ) -> Result<Option<Parsed<ParsedRequest<'a>>>, HandshakeError> {
+        let Some((start_line, headers, consumed_bytes)) = split_head(buf, max_head_length)? else {
+            return Ok(None);
+        };
+        let mut parts = start_line.split(' ');
+        let (Some(method), Some(path), Some(version), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(HandshakeError::Malformed);
+        };
+        if method != "GET" {
+            return Err(HandshakeError::NotGet);
+        }
+        if version != "HTTP/1.1" {
+            return Err(HandshakeError::UnsupportedHttpVersion);
+        }
+        check_upgrade_headers(&headers)?;
+        if headers.get("Sec-WebSocket-Version") != Some("13") {
+            return Err(HandshakeError::UnsupportedWebsocketVersion);
+        }
+        let key = headers
+            .get_unique("Sec-WebSocket-Key")?
+            .map(str::as_bytes)
+            .filter(|k| is_base64(k))
+            .and_then(|k| <[u8; 24]>::try_from(k).ok())
+            .ok_or(HandshakeError::InvalidKey)?;
+        Ok(Some(Parsed {
+            value: ParsedRequest {
+                path,
+                host: headers.get("Host"),
+                key,
+                origin: headers.get("Origin"),
+                protocols: HeaderValues {
+                    headers,
+                    name: "Sec-WebSocket-Protocol",
+                },
+                extensions: HeaderValues {
+                    headers,
+                    name: "Sec-WebSocket-Extensions",
+                },
+                headers,
+            },
+            consumed_bytes,
+        }))
+    }
+
+    /// Compute `Sec-WebSocket-Accept` value for the response to this request.
+    #[inline]
+    pub const fn accept_key(&self) -> [u8; 28] {
+        compute_accept_key(&self.key)
+    }
+}
+
+/// Server's `101 Switching Protocols` response, to be written with [`ServerResponse::write`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ServerResponse<'a> {
+    /// Value for the `Sec-WebSocket-Accept` header, derived from client's key.
+    pub accept: [u8; 28],
+    /// Selected subprotocol, if any.
+    pub protocol: Option<&'a str>,
+    /// Value for the `Sec-WebSocket-Extensions` header, if any.
+    pub extensions: Option<&'a str>,
+    /// Additional headers.
+    pub extra_headers: &'a [(&'a str, &'a str)],
+}
+
+impl<'a> ServerResponse<'a> {
+    /// Write the response into `out` buffer, returning number of bytes written.
+    pub fn write(&self, out: &mut [u8]) -> Result<usize, HandshakeError> {
+        let mut w = HeadWriter { buf: out, len: 0 };
+        w.put(b"HTTP/1.1 101 Switching Protocols\r\n")?;
+        w.header("Upgrade", b"websocket")?;
+        w.header("Connection", b"Upgrade")?;
+        w.header("Sec-WebSocket-Accept", &self.accept)?;
+        w.optional_header("Sec-WebSocket-Protocol", self.protocol)?;
+        w.optional_header("Sec-WebSocket-Extensions", self.extensions)?;
+        for (name, value) in self.extra_headers {
+            w.header(name, value.as_bytes())?;
+        }
+        w.put(b"\r\n")?;
+        Ok(w.len)
+    }
+}
+
+/// Server's response to the opening handshake, as parsed by the client using [`ParsedResponse::parse`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ParsedResponse<'a> {
+    /// Value of the `Sec-WebSocket-Accept` header.
+    pub accept: [u8; 28],
+    /// Value of the `Sec-WebSocket-Protocol` header.
+    pub protocol: Option<&'a str>,
+    /// Value of the `Sec-WebSocket-Extensions` header.
+    pub extensions: Option<&'a str>,
+    /// All headers of the response.
+    pub headers: HttpHeaders<'a>,
+}
+
+impl<'a> ParsedResponse<'a> {
+    /// Try to parse a response from bytes received so far.
+    ///
+    /// Returns `Ok(None)` if the response head is not complete yet. In that case,
+    /// read more bytes from the socket, append them to the buffer and try again.
+    ///
+    /// Checks status code and `Upgrade` and `Connection` headers, and rejects repeated `Sec-WebSocket-Accept`,
+    /// `Sec-WebSocket-Protocol` and `Sec-WebSocket-Extensions` headers (see RFC 6455 section 11.3), but
+    /// not whether `Sec-WebSocket-Accept` matches the key. Use [`ParsedResponse::verify_key`] for that.
+    ///
+    /// Response head longer than [`DEFAULT_MAX_HEAD_LENGTH`] is rejected with [`HandshakeError::HeadTooLong`].
+    pub fn parse(buf: &'a [u8]) -> Result<Option<Parsed<ParsedResponse<'a>>>, HandshakeError> {
+        ParsedResponse::parse_with_max_head_length(buf, DEFAULT_MAX_HEAD_LENGTH)
+    }
+
+    /// Like [`ParsedResponse::parse`], but with a custom limit on the response head length.
+    pub fn parse_with_max_head_length(
+        buf: &'a [u8],
+        max_head_length: usize,
+    ) -> Result<Option<Parsed<ParsedResponse<'a>>>, HandshakeError> {
+        let Some((start_line, headers, consumed_bytes)) = split_head(buf, max_head_length)? else {
+            return Ok(None);
+        };
+        let mut parts = start_line.splitn(3, ' ');
+        let (Some(version), Some(status)) = (parts.next(), parts.next()) else {
+            return Err(HandshakeError::Malformed);
+        };
+        if version != "HTTP/1.1" {
+            return Err(HandshakeError::UnsupportedHttpVersion);
+        }
+        let status: u16 = status.parse().map_err(|_| HandshakeError::Malformed)?;
+        if status != 101 {
+            return Err(HandshakeError::UnexpectedStatus(status));
+        }
+        check_upgrade_headers(&headers)?;
+        let accept = headers
+            .get_unique("Sec-WebSocket-Accept")?
+            .map(str::as_bytes)
+            .filter(|k| is_base64(k))
+            .and_then(|k| <[u8; 28]>::try_from(k).ok())
+            .ok_or(HandshakeError::InvalidAccept)?;
+        Ok(Some(Parsed {
+            value: ParsedResponse {
+                accept,
+                protocol: headers.get_unique("Sec-WebSocket-Protocol")?,
+                extensions: headers.get_unique("Sec-WebSocket-Extensions")?,
+                headers,
+            },
+            consumed_bytes,
+        }))
+    }
+
+    /// Check that `Sec-WebSocket-Accept` corresponds to `Sec-WebSocket-Key` we have sent.
+    pub fn verify_key(&self, key: &[u8; 24]) -> Result<(), HandshakeError> {
+        if verify_accept_key(key, &self.accept) {
+            Ok(())
+        } else {
+            Err(HandshakeError::InvalidAccept)
+        }
+    }
+}
@@ -0,0 +1,139 @@
+extern crate std;
+
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+const RFC_REQUEST: &[u8] = b"GET /chat HTTP/1.1\r\n\
+Host: server.example.com\r\n\
+Upgrade: websocket\r\n\
+Connection: keep-alive, Upgrade\r\n\
+Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+Origin: http://example.com\r\n\
+Sec-WebSocket-Protocol: chat, superchat\r\n\
+Sec-WebSocket-Version: 13\r\n\
+\r\n";
+
+#[test]
+fn handshake_client_request_roundtrip() {
+    let mut buf = [0u8; 512];
+    let req = ClientRequest {
+        path: "/chat",
+        host: "server.example.com",
+        key: *b"dGhlIHNhbXBsZSBub25jZQ==",
+        origin: Some("http://example.com"),
+        protocols: Some("chat, superchat"),
+        extensions: None,
+        extra_headers: &[("Authorization", "Bearer 123")],
+    };
+    let n = req.write(&mut buf).unwrap();
+    let parsed = ParsedRequest::parse(&buf[..n]).unwrap().unwrap();
+    assert_eq!(parsed.consumed_bytes, n);
+    assert_eq!(parsed.value.path, "/chat");
+    assert_eq!(parsed.value.host, Some("server.example.com"));
+    assert_eq!(&parsed.value.key, b"dGhlIHNhbXBsZSBub25jZQ==");
+    assert_eq!(parsed.value.protocols.iter().collect::<std::vec::Vec<_>>(), ["chat, superchat"]);
+    assert!(parsed.value.extensions.is_empty());
+    assert_eq!(parsed.value.headers.get("authorization"), Some("Bearer 123"));
+
+    assert_eq!(req.write(&mut buf[..100]), Err(HandshakeError::BufferTooSmall));
+    let evil = ClientRequest { host: "x\r\nEvil: 1", ..req };
+    assert_eq!(evil.write(&mut buf), Err(HandshakeError::InvalidHeaderValue));
+}
+
+#[test]
+fn handshake_parse_request_incrementally() {
+    for i in 0..RFC_REQUEST.len() {
+        assert_eq!(ParsedRequest::parse(&RFC_REQUEST[..i]), Ok(None));
+    }
+    let mut buf = std::vec::Vec::from(RFC_REQUEST);
+    buf.extend_from_slice(b"\x81\x00");
+    let parsed = ParsedRequest::parse(&buf).unwrap().unwrap();
+    assert_eq!(&buf[parsed.consumed_bytes..], b"\x81\x00");
+    assert_eq!(parsed.value.origin, Some("http://example.com"));
+}
+
+#[test]
+fn handshake_max_head_length() {
+    let n = RFC_REQUEST.len();
+    assert!(ParsedRequest::parse_with_max_head_length(RFC_REQUEST, n).unwrap().is_some());
+    assert_eq!(ParsedRequest::parse_with_max_head_length(RFC_REQUEST, n - 1), Err(HandshakeError::HeadTooLong));
+    assert_eq!(ParsedRequest::parse_with_max_head_length(&RFC_REQUEST[..(n - 2)], n - 1), Ok(None));
+    assert_eq!(ParsedRequest::parse_with_max_head_length(&RFC_REQUEST[..(n - 1)], n - 1), Err(HandshakeError::HeadTooLong));
+
+    let endless = std::vec![b'a'; DEFAULT_MAX_HEAD_LENGTH];
+    assert_eq!(ParsedRequest::parse(&endless[..(DEFAULT_MAX_HEAD_LENGTH - 1)]), Ok(None));
+    assert_eq!(ParsedRequest::parse(&endless), Err(HandshakeError::HeadTooLong));
+    assert_eq!(ParsedResponse::parse(&endless), Err(HandshakeError::HeadTooLong));
+}
+
+#[test]
+fn handshake_bad_requests() {
+    assert_eq!(ParsedRequest::parse(b"POST / HTTP/1.1\r\n\r\n"), Err(HandshakeError::NotGet));
+    assert_eq!(ParsedRequest::parse(b"GET / HTTP/1.0\r\n\r\n"), Err(HandshakeError::UnsupportedHttpVersion));
+    assert_eq!(ParsedRequest::parse(b"GET / HTTP/1.1\r\nHost: x\r\n\r\n"), Err(HandshakeError::MissingUpgrade));
+    assert_eq!(ParsedRequest::parse(b"GET / HTTP/1.1\r\nUpgrade: WebSocket\r\n\r\n"), Err(HandshakeError::MissingConnectionUpgrade));
+    assert_eq!(ParsedRequest::parse(b"GET / HTTP/1.1\r\nUpgrade: websocket\r\nConnection: upgrade\r\nSec-WebSocket-Version: 8\r\n\r\n"), Err(HandshakeError::UnsupportedWebsocketVersion));
+    assert_eq!(ParsedRequest::parse(b"GET / HTTP/1.1\r\nUpgrade: websocket\r\nConnection: upgrade\r\nSec-WebSocket-Version: 13\r\nSec-WebSocket-Key: short\r\n\r\n"), Err(HandshakeError::InvalidKey));
+    assert_eq!(ParsedRequest::parse(b"GET / HTTP/1.1\r\nbroken\r\n\r\n"), Err(HandshakeError::Malformed));
+}
+
+#[test]
+fn handshake_server_response_roundtrip() {
+    let mut buf = [0u8; 512];
+    let resp = ServerResponse {
+        accept: *b"s3pPLMBiTxaQ9kYGzzhZRbK+xOo=",
+        protocol: Some("chat"),
+        extensions: None,
+        extra_headers: &[],
+    };
+    let n = resp.write(&mut buf).unwrap();
+    assert_eq!(&buf[..n], &b"HTTP/1.1 101 Switching Protocols\r\n\
+Upgrade: websocket\r\n\
+Connection: Upgrade\r\n\
+Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\
+Sec-WebSocket-Protocol: chat\r\n\
+\r\n"[..]);
+    for i in 0..n {
+        assert_eq!(ParsedResponse::parse(&buf[..i]), Ok(None));
+    }
+    let parsed = ParsedResponse::parse(&buf[..n]).unwrap().unwrap();
+    assert_eq!(parsed.consumed_bytes, n);
+    assert_eq!(&parsed.value.accept, b"s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    assert_eq!(parsed.value.protocol, Some("chat"));
+
+    assert_eq!(ParsedResponse::parse(b"HTTP/1.1 403 Forbidden\r\n\r\n"), Err(HandshakeError::UnexpectedStatus(403)));
+    assert_eq!(ParsedResponse::parse(b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\r\n"), Err(HandshakeError::InvalidAccept));
+}
+
+#[test]
+fn handshake_repeated_headers() {
+    const RESPONSE: &str = "HTTP/1.1 101 Switching Protocols\r\n\
+Upgrade: websocket\r\n\
+Connection: Upgrade\r\n\
+Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\
+Sec-WebSocket-Extensions: foo\r\n";
+    let single = std::format!("{RESPONSE}\r\n");
+    let parsed = ParsedResponse::parse(single.as_bytes()).unwrap().unwrap();
+    assert_eq!(parsed.value.extensions, Some("foo"));
+    let repeated = std::format!("{RESPONSE}Sec-WebSocket-Extensions: permessage-deflate\r\n\r\n");
+    assert_eq!(ParsedResponse::parse(repeated.as_bytes()), Err(HandshakeError::RepeatedHeader));
+    let repeated = std::format!("{RESPONSE}Sec-WebSocket-Protocol: a\r\nsec-websocket-protocol: b\r\n\r\n");
+    assert_eq!(ParsedResponse::parse(repeated.as_bytes()), Err(HandshakeError::RepeatedHeader));
+
+    // Requests may split the lists over several headers.
+    let request = b"GET / HTTP/1.1\r\n\
+Upgrade: websocket\r\n\
+Connection: Upgrade\r\n\
+Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+Sec-WebSocket-Version: 13\r\n\
+Sec-WebSocket-Extensions: foo\r\n\
+Sec-WebSocket-Extensions: bar; x=1\r\n\
+\r\n";
+    let parsed = ParsedRequest::parse(request).unwrap().unwrap();
+    assert_eq!(parsed.value.extensions.iter().collect::<std::vec::Vec<_>>(), ["foo", "bar; x=1"]);
+    assert!(parsed.value.protocols.is_empty());
+    let mut twice = std::vec::Vec::from(&request[..]);
+    twice.splice(16..16, b"Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n".iter().copied());
+    assert_eq!(ParsedRequest::parse(&twice), Err(HandshakeError::RepeatedHeader));
+}
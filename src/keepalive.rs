@@ -0,0 +1,131 @@
+use crate::{FrameInfo, Opcode, WebsocketFrameEvent};
+
+/// Timeouts used by [`KeepaliveTimer`]. All values are in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeepaliveConfig {
+    /// Send a [`Opcode::Ping`] when nothing was received from the peer for this long.
+    /// `None` disables sending pings.
+    pub ping_interval: Option<u64>,
+    /// Consider the connection dead if no [`Opcode::Pong`] arrived this long after sending a Ping.
+    pub pong_timeout: u64,
+    /// Give up on the closing handshake if it is not finished this long after a close frame was sent or received.
+    pub close_timeout: u64,
+}
+
+impl Default for KeepaliveConfig {
+    fn default() -> Self {
+        KeepaliveConfig {
+            ping_interval: Some(30_000),
+            pong_timeout: 10_000,
+            close_timeout: 5_000,
+        }
+    }
+}
+
+/// What the caller should do, as reported by [`KeepaliveTimer::poll`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum KeepaliveAction {
+    /// Nothing to do until [`KeepaliveTimer::next_deadline`].
+    Wait,
+    /// Connection is idle, a Ping frame should be sent.
+    SendPing,
+    /// Pong was not received in time. The connection should be considered dead.
+    PongOverdue,
+    /// Closing handshake was not completed in time. TCP connection should be closed.
+    CloseTimedOut,
+}
+
+/// Sans-IO timer logic for detecting idle and dead connections.
+///
+/// Time is an abstract monotonic `u64` number of milliseconds supplied by the caller.
+///
+/// Feed it events from [`crate::WebsocketFrameDecoder`] using [`KeepaliveTimer::incoming_event`],
+/// headers of frames written with [`crate::WebsocketFrameEncoder`] using [`KeepaliveTimer::outgoing_frame`]
+/// and periodically (e.g. at [`KeepaliveTimer::next_deadline`]) call [`KeepaliveTimer::poll`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepaliveTimer {
+    config: KeepaliveConfig,
+    last_received: u64,
+    ping_sent_at: Option<u64>,
+    close_started_at: Option<u64>,
+}
+
+impl KeepaliveTimer {
+    /// Create new instance. `now` is the moment the connection was established.
+    #[inline]
+    pub const fn new(config: KeepaliveConfig, now: u64) -> Self {
+        KeepaliveTimer {
+            config,
+            last_received: now,
+            ping_sent_at: None,
+            close_started_at: None,
+        }
+    }
+
+    /// Observe an incoming frame event. Any incoming data counts as connection activity.
+    pub fn incoming_event(&mut self, now: u64, event: &WebsocketFrameEvent) {
+        self.last_received = now;
+        if let WebsocketFrameEvent::End { frame_info, .. } = event {
+            match frame_info.opcode {
+                Opcode::Pong => self.ping_sent_at = None,
+                Opcode::ConnectionClose => self.start_close(now),
+                _ => (),
+            }
+        }
+    }
+
+    /// Register a frame which is being sent.
+    pub fn outgoing_frame(&mut self, now: u64, frame_info: &FrameInfo) {
+        match frame_info.opcode {
+            Opcode::Ping => {
+                self.ping_sent_at.get_or_insert(now);
+            }
+            Opcode::ConnectionClose => self.start_close(now),
+            _ => (),
+        }
+    }
+
+    fn start_close(&mut self, now: u64) {
+        self.close_started_at.get_or_insert(now);
+    }
+
+    /// Check timeouts.
+    ///
+    /// [`KeepaliveAction::SendPing`] is returned repeatedly until the Ping frame is
+    /// registered with [`KeepaliveTimer::outgoing_frame`].
+    pub fn poll(&self, now: u64) -> KeepaliveAction {
+        if let Some(t) = self.close_started_at {
+            if now >= t.saturating_add(self.config.close_timeout) {
+                return KeepaliveAction::CloseTimedOut;
+            }
+            return KeepaliveAction::Wait;
+        }
+        if let Some(t) = self.ping_sent_at {
+            if now >= t.saturating_add(self.config.pong_timeout) {
+                return KeepaliveAction::PongOverdue;
+            }
+            return KeepaliveAction::Wait;
+        }
+        if let Some(interval) = self.config.ping_interval {
+            if now >= self.last_received.saturating_add(interval) {
+                return KeepaliveAction::SendPing;
+            }
+        }
+        KeepaliveAction::Wait
+    }
+
+    /// Moment of the next time [`KeepaliveTimer::poll`] may return something other than [`KeepaliveAction::Wait`].
+    ///
+    /// `None` means there is no deadline (e.g. pings are disabled).
+    pub fn next_deadline(&self) -> Option<u64> {
+        if let Some(t) = self.close_started_at {
+            return Some(t.saturating_add(self.config.close_timeout));
+        }
+        if let Some(t) = self.ping_sent_at {
+            return Some(t.saturating_add(self.config.pong_timeout));
+        }
+        self.config
+            .ping_interval
+            .map(|interval| self.last_received.saturating_add(interval))
+    }
+}
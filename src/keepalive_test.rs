@@ -0,0 +1,58 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+const CONFIG: KeepaliveConfig = KeepaliveConfig {
+    ping_interval: Some(1000),
+    pong_timeout: 500,
+    close_timeout: 200,
+};
+
+fn frame(opcode: Opcode) -> FrameInfo {
+    FrameInfo { opcode, payload_length: 0, mask: None, fin: true, reserved: 0 }
+}
+
+fn end(opcode: Opcode) -> WebsocketFrameEvent {
+    WebsocketFrameEvent::End { frame_info: frame(opcode), original_opcode: opcode }
+}
+
+#[test]
+fn keepalive_ping_pong() {
+    let mut t = KeepaliveTimer::new(CONFIG, 100);
+    assert_eq!(t.next_deadline(), Some(1100));
+    assert_eq!(t.poll(500), KeepaliveAction::Wait);
+    t.incoming_event(600, &end(Opcode::Binary));
+    assert_eq!(t.poll(1100), KeepaliveAction::Wait);
+    assert_eq!(t.poll(1600), KeepaliveAction::SendPing);
+    assert_eq!(t.poll(1601), KeepaliveAction::SendPing);
+    t.outgoing_frame(1602, &frame(Opcode::Ping));
+    assert_eq!(t.next_deadline(), Some(2102));
+    assert_eq!(t.poll(2000), KeepaliveAction::Wait);
+    t.incoming_event(2001, &end(Opcode::Pong));
+    assert_eq!(t.poll(2200), KeepaliveAction::Wait);
+    assert_eq!(t.next_deadline(), Some(3001));
+}
+
+#[test]
+fn keepalive_pong_overdue() {
+    let mut t = KeepaliveTimer::new(CONFIG, 0);
+    t.outgoing_frame(1000, &frame(Opcode::Ping));
+    t.incoming_event(1200, &end(Opcode::Text));
+    assert_eq!(t.poll(1499), KeepaliveAction::Wait);
+    assert_eq!(t.poll(1500), KeepaliveAction::PongOverdue);
+}
+
+#[test]
+fn keepalive_close_timeout() {
+    let mut t = KeepaliveTimer::new(CONFIG, 0);
+    t.outgoing_frame(50, &frame(Opcode::ConnectionClose));
+    assert_eq!(t.next_deadline(), Some(250));
+    assert_eq!(t.poll(249), KeepaliveAction::Wait);
+    assert_eq!(t.poll(5000), KeepaliveAction::CloseTimedOut);
+
+    let mut t = KeepaliveTimer::new(KeepaliveConfig { ping_interval: None, ..CONFIG }, 0);
+    assert_eq!(t.next_deadline(), None);
+    assert_eq!(t.poll(u64::MAX), KeepaliveAction::Wait);
+    t.incoming_event(10, &end(Opcode::ConnectionClose));
+    assert_eq!(t.poll(210), KeepaliveAction::CloseTimedOut);
+}
@@ -7,6 +7,12 @@
 
 #![no_std]
 
+#[cfg(feature="alloc")]
+extern crate alloc;
+
+#[cfg(feature="std")]
+extern crate std;
+
 mod masking;
 
 /// Apply WebSocket masking to the giben block of data.
@@ -25,10 +31,66 @@ pub type PayloadLength = u64;
 #[cfg(not(feature="large_frames"))]
 pub type PayloadLength = u16;
 
+mod role;
+pub use role::{MaskingKeySource, Role};
 mod frame_encoding;
-pub use frame_encoding::WebsocketFrameEncoder;
+pub use frame_encoding::{encode_frame_header, FrameEncoderError, WebsocketFrameEncoder, WebsocketFrameWriter, WebsocketFrameWriterResult};
 mod frame_decoding;
-pub use frame_decoding::{FrameDecoderError,WebsocketFrameDecoder, WebsocketFrameEvent,WebsocketFrameDecoderAddDataResult};
+pub use frame_decoding::{FrameDecoderError, FrameDecoderStrictness, WebsocketFrameDecoder, WebsocketFrameEvent,WebsocketFrameDecoderAddDataResult, WebsocketFrameDecoderCopyResult, WebsocketFrameEvents};
+mod message_decoding;
+pub use message_decoding::{MessageDecoderError, WebsocketMessageDecoder, WebsocketMessageEvent, WebsocketMessageDecoderAddDataResult};
+#[cfg(feature="alloc")]
+pub use message_decoding::{WebsocketMessage, WebsocketMessageAssembler, WebsocketMessageAssemblerAddDataResult};
+mod message_encoding;
+pub use message_encoding::{MessageWriterError, WebsocketMessageWriter};
+mod utf8_validation;
+pub use utf8_validation::{Utf8Error, Utf8ValidationMode, Utf8Validator};
+mod validation;
+pub use validation::{DecoderLimit, DecoderLimits, ProtocolViolation, ValidatingFrameDecoder, MAX_CONTROL_PAYLOAD_LENGTH};
+mod close_frame;
+pub use close_frame::{CloseCode, ClosePayload, ClosePayloadError, MAX_CLOSE_REASON_LENGTH};
+mod close_handshake;
+pub use close_handshake::{CloseHandshake, CloseHandshakeAction, CloseHandshakeError, CloseState};
+mod ping_pong;
+pub use ping_pong::{PongFrame, PongResponder};
+mod keepalive;
+pub use keepalive::{KeepaliveAction, KeepaliveConfig, KeepaliveTimer};
+mod accept_key;
+pub use accept_key::{compute_accept_key, generate_client_key, verify_accept_key};
+mod handshake;
+pub use handshake::{ClientRequest, HandshakeError, DEFAULT_MAX_HEAD_LENGTH, HeaderValues, HttpHeaders, Parsed, ParsedRequest, ParsedResponse, ServerResponse};
+mod extension;
+pub use extension::{Extension, RSV1, RSV2, RSV3};
+mod negotiation;
+pub use negotiation::{negotiate_extensions, select_subprotocol, verify_extensions, verify_subprotocol, ExtensionOffer, ExtensionOffers, ExtensionParam, ExtensionParams};
+#[cfg(any(feature="std", feature="embedded_io", feature="embedded_io_async"))]
+mod connection;
+#[cfg(feature="std")]
+mod stream;
+#[cfg(feature="std")]
+pub use stream::{WebSocketStream, WebSocketStreamMessageReader, WebSocketStreamMessageWriter};
+#[cfg(any(feature="tokio", feature="futures_io"))]
+mod async_stream;
+#[cfg(feature="tokio")]
+mod tokio_stream;
+#[cfg(feature="tokio")]
+pub use tokio_stream::TokioWebSocketStream;
+#[cfg(feature="futures_io")]
+mod futures_stream;
+#[cfg(feature="futures_io")]
+pub use futures_stream::FuturesWebSocketStream;
+#[cfg(any(feature="embedded_io", feature="embedded_io_async"))]
+mod embedded;
+#[cfg(any(feature="embedded_io", feature="embedded_io_async"))]
+pub use embedded::EmbeddedWebSocketError;
+#[cfg(feature="embedded_io")]
+pub use embedded::EmbeddedWebSocket;
+#[cfg(feature="embedded_io_async")]
+pub use embedded::EmbeddedAsyncWebSocket;
+#[cfg(feature="permessage_deflate")]
+mod permessage_deflate;
+#[cfg(feature="permessage_deflate")]
+pub use permessage_deflate::{DeflateStreamResult, PerMessageDeflate, PerMessageDeflateError, PerMessageDeflateParams, PERMESSAGE_DEFLATE};
 
 /// WebSocket frame type.
 /// 
@@ -76,7 +138,31 @@ pub enum Opcode {
 }
 
 impl Opcode {
+    /// Get opcode from the lower 4 bits of the first frame header byte.
+    pub(crate) const fn from_u8(x: u8) -> Opcode {
+        use Opcode::*;
+        match x & 0xF {
+            0 => Continuation,
+            1 => Text,
+            2 => Binary,
+            3 => ReservedData3,
+            4 => ReservedData4,
+            5 => ReservedData5,
+            6 => ReservedData6,
+            7 => ReservedData7,
+            8 => ConnectionClose,
+            9 => Ping,
+            0xA => Pong,
+            0xB => ReservedControlB,
+            0xC => ReservedControlC,
+            0xD => ReservedControlD,
+            0xE => ReservedControlE,
+            _ => ReservedControlF,
+        }
+    }
+
     /// Check if this opcode is of a data frame.
+    #[allow(clippy::match_like_matches_macro)]
     pub fn is_data(&self) -> bool {
         match self {
             Opcode::Continuation => true,
@@ -120,7 +206,7 @@ impl FrameInfo {
     /// 
     /// Does not check for valitity of a frame within a sequence of frames,
     /// e.g. for orphaned [`Opcode::Continuation`] frames or
-    /// for unfinished prior messages.
+    /// for unfinished prior messages. Use [`ValidatingFrameDecoder`] for that.
     pub const fn is_reasonable(&self) -> bool {
         if self.reserved != 0 { return false; }
         match self.opcode {
@@ -143,9 +229,65 @@ pub const MAX_HEADER_LENGTH: usize = 2 + 8 + 4;
 #[cfg(not(feature = "large_frames"))]
 pub const MAX_HEADER_LENGTH: usize = 2 + 2 + 4;
 
+#[cfg(test)]
+mod accept_key_test;
+
+#[cfg(test)]
+mod close_frame_test;
+
+#[cfg(test)]
+mod close_handshake_test;
+
+#[cfg(all(test, any(feature="std", feature="embedded_io", feature="embedded_io_async")))]
+mod connection_test;
+
 #[cfg(test)]
 mod decoding_test;
 
+#[cfg(all(test, any(feature="embedded_io", feature="embedded_io_async")))]
+mod embedded_test;
+
+#[cfg(test)]
+mod encoding_test;
+
+#[cfg(test)]
+mod extension_test;
+
 #[cfg(test)]
 mod frame_roundtrip_test;
 
+#[cfg(all(test, feature="futures_io"))]
+mod futures_stream_test;
+
+#[cfg(test)]
+mod handshake_test;
+
+#[cfg(test)]
+mod keepalive_test;
+
+#[cfg(test)]
+mod message_decoding_test;
+#[cfg(test)]
+mod message_encoding_test;
+
+#[cfg(all(test, feature="permessage_deflate"))]
+mod permessage_deflate_test;
+
+#[cfg(test)]
+mod negotiation_test;
+
+#[cfg(test)]
+mod ping_pong_test;
+
+#[cfg(all(test, feature="std"))]
+mod stream_test;
+#[cfg(test)]
+mod test_util;
+#[cfg(all(test, feature="tokio"))]
+mod tokio_stream_test;
+#[cfg(test)]
+mod utf8_validation_test;
+
+#[cfg(test)]
+mod validation_test;
+
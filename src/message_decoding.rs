@@ -0,0 +1,351 @@
+use crate::{FrameDecoderError, FrameInfo, Opcode, WebsocketFrameDecoder, WebsocketFrameEvent};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Error from [`WebsocketMessageDecoder`], when sequence of frames cannot be assembled into messages.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum MessageDecoderError {
+    /// Underlying [`WebsocketFrameDecoder`] failed.
+    FrameDecoder(FrameDecoderError),
+    /// [`Opcode::Continuation`] frame arrived when there is no unfinished message.
+    OrphanContinuation,
+    /// A new data frame (e.g. [`Opcode::Text`]) arrived when previous message is not finished yet.
+    UnfinishedMessage,
+    /// Message exceeds the maximum size set with [`WebsocketMessageAssembler::set_max_message_size`].
+    MessageTooLarge,
+}
+
+impl From<FrameDecoderError> for MessageDecoderError {
+    fn from(value: FrameDecoderError) -> Self {
+        MessageDecoderError::FrameDecoder(value)
+    }
+}
+
+/// Information that [`WebsocketMessageDecoder`] gives in return to bytes being fed to it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum WebsocketMessageEvent {
+    /// New data message is started. `opcode` is [`Opcode::Text`], [`Opcode::Binary`] or a reserved data opcode.
+    MessageStart {
+        #[allow(missing_docs)]
+        opcode: Opcode,
+    },
+    /// Bytes which were supplied to [`WebsocketMessageDecoder::add_data`] are message payload bytes.
+    ///
+    /// Use [`WebsocketMessageDecoderAddDataResult::consumed_bytes`] to get actual
+    /// buffer to be handled as content of the message, like with [`WebsocketFrameEvent::PayloadChunk`].
+    MessageChunk {
+        #[allow(missing_docs)]
+        opcode: Opcode,
+    },
+    /// All chunks of the data message are delivered.
+    MessageEnd {
+        #[allow(missing_docs)]
+        opcode: Opcode,
+    },
+    /// Frame-level event of a control frame (e.g. [`Opcode::Ping`]), which may appear
+    /// between frames of a data message.
+    ///
+    /// Payload of control frames is delivered the same way as for [`WebsocketFrameDecoder`].
+    Control(WebsocketFrameEvent),
+}
+
+/// Return value of [`WebsocketMessageDecoder::add_data`] call.
+#[derive(Debug, Clone)]
+pub struct WebsocketMessageDecoderAddDataResult {
+    /// Indicates how many bytes were consumed and should not be supplied again to
+    /// the subsequent invocation of [`WebsocketMessageDecoder::add_data`].
+    ///
+    /// When `add_data` produces [`WebsocketMessageEvent::MessageChunk`] or a `Control` event with
+    /// [`WebsocketFrameEvent::PayloadChunk`], it also indicates how many
+    /// of the bytes in the buffer (starting from 0) should be used as a part of payload.
+    pub consumed_bytes: usize,
+    /// Emitted event, if any.
+    pub event: Option<WebsocketMessageEvent>,
+}
+
+/// A WebSocket message decoder, assembling messages from frames coming from [`WebsocketFrameDecoder`].
+///
+/// It hides frame boundaries of data messages, tracking original opcode and `fin` flag
+/// across [`Opcode::Continuation`] frames, and reports control frames separately.
+///
+/// It is used the same way as [`WebsocketFrameDecoder`]: call [`WebsocketMessageDecoder::add_data`]
+/// again when new data is available, when previous call consumed nonzero number of bytes
+/// or when previous call returned an event.
+///
+/// It does not allocate memory. See `WebsocketMessageAssembler` (requires `alloc` crate feature)
+/// for a variant that returns whole messages.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WebsocketMessageDecoder {
+    frame_decoder: WebsocketFrameDecoder,
+    current_message: Option<Opcode>,
+}
+
+impl WebsocketMessageDecoder {
+    /// Create new instance.
+    #[inline]
+    pub const fn new() -> Self {
+        WebsocketMessageDecoder {
+            frame_decoder: WebsocketFrameDecoder::new(),
+            current_message: None,
+        }
+    }
+
+    /// Add some bytes to the decoder and return events, if any.
+    ///
+    /// Input buffer needs to be mutable because it is also used to transform (unmask)
+    /// payload content chunks in-place.
+    pub fn add_data(
+        &mut self,
+        data: &mut [u8],
+    ) -> Result<WebsocketMessageDecoderAddDataResult, MessageDecoderError> {
+        loop {
+            let ret = self.frame_decoder.add_data(data)?;
+            let Some(ev) = ret.event else {
+                return Ok(WebsocketMessageDecoderAddDataResult {
+                    consumed_bytes: ret.consumed_bytes,
+                    event: None,
+                });
+            };
+            let event = self.handle_frame_event(ev)?;
+            if event.is_some() || ret.consumed_bytes > 0 {
+                return Ok(WebsocketMessageDecoderAddDataResult {
+                    consumed_bytes: ret.consumed_bytes,
+                    event,
+                });
+            }
+        }
+    }
+
+    fn handle_frame_event(
+        &mut self,
+        ev: WebsocketFrameEvent,
+    ) -> Result<Option<WebsocketMessageEvent>, MessageDecoderError> {
+        let opcode = match ev {
+            WebsocketFrameEvent::Start { frame_info, .. } => frame_info.opcode,
+            WebsocketFrameEvent::PayloadChunk { original_opcode } => original_opcode,
+            WebsocketFrameEvent::End { frame_info, .. } => frame_info.opcode,
+        };
+        if opcode.is_control() {
+            return Ok(Some(WebsocketMessageEvent::Control(ev)));
+        }
+        Ok(match ev {
+            WebsocketFrameEvent::Start {
+                frame_info: FrameInfo { opcode: Opcode::Continuation, .. },
+                ..
+            } => {
+                if self.current_message.is_none() {
+                    return Err(MessageDecoderError::OrphanContinuation);
+                }
+                None
+            }
+            WebsocketFrameEvent::Start { frame_info, .. } => {
+                if self.current_message.is_some() {
+                    return Err(MessageDecoderError::UnfinishedMessage);
+                }
+                self.current_message = Some(frame_info.opcode);
+                Some(WebsocketMessageEvent::MessageStart {
+                    opcode: frame_info.opcode,
+                })
+            }
+            WebsocketFrameEvent::PayloadChunk { .. } => self
+                .current_message
+                .map(|opcode| WebsocketMessageEvent::MessageChunk { opcode }),
+            WebsocketFrameEvent::End { frame_info, .. } => {
+                if frame_info.fin {
+                    self.current_message
+                        .take()
+                        .map(|opcode| WebsocketMessageEvent::MessageEnd { opcode })
+                } else {
+                    None
+                }
+            }
+        })
+    }
+
+    /// Opcode of a data message which is started, but not finished yet.
+    #[inline]
+    pub const fn current_message(&self) -> Option<Opcode> {
+        self.current_message
+    }
+
+    /// There is no incomplete WebSocket frame or message at this moment and EOF is valid here.
+    #[inline]
+    pub fn eof_valid(&self) -> bool {
+        self.current_message.is_none() && self.frame_decoder.eof_valid()
+    }
+}
+
+/// Complete WebSocket message (or a control frame), as returned by [`WebsocketMessageAssembler`].
+#[cfg(feature = "alloc")]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default)]
+pub struct WebsocketMessage {
+    /// Type of the message. Never [`Opcode::Continuation`].
+    pub opcode: Opcode,
+    /// Unmasked content of the message.
+    pub payload: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl WebsocketMessage {
+    /// Create a [`Opcode::Text`] message.
+    pub fn text(s: impl Into<alloc::string::String>) -> Self {
+        WebsocketMessage {
+            opcode: Opcode::Text,
+            payload: s.into().into_bytes(),
+        }
+    }
+
+    /// Create a [`Opcode::Binary`] message.
+    pub fn binary(payload: impl Into<Vec<u8>>) -> Self {
+        WebsocketMessage {
+            opcode: Opcode::Binary,
+            payload: payload.into(),
+        }
+    }
+
+    /// Check if this is a control message (e.g. [`Opcode::Ping`]).
+    #[inline]
+    pub fn is_control(&self) -> bool {
+        self.opcode.is_control()
+    }
+}
+
+/// Return value of [`WebsocketMessageAssembler::add_data`] call.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct WebsocketMessageAssemblerAddDataResult {
+    /// Indicates how many bytes were consumed and should not be supplied again to
+    /// the subsequent invocation of [`WebsocketMessageAssembler::add_data`].
+    pub consumed_bytes: usize,
+    /// A message which got completed, if any.
+    pub message: Option<WebsocketMessage>,
+}
+
+/// Allocating variant of [`WebsocketMessageDecoder`] which accumulates whole messages in `Vec<u8>`s.
+///
+/// Control frames are returned as separate messages, possibly in the middle of
+/// a fragmented data message.
+///
+/// Call [`WebsocketMessageAssembler::add_data`] again until it returns zero
+/// [`WebsocketMessageAssemblerAddDataResult::consumed_bytes`] and no message.
+///
+/// Message size is unlimited by default, use [`WebsocketMessageAssembler::set_max_message_size`]
+/// when receiving from untrusted peers.
+///
+/// Only available with `alloc` crate feature.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct WebsocketMessageAssembler {
+    decoder: WebsocketMessageDecoder,
+    message: Vec<u8>,
+    control: Vec<u8>,
+    max_message_size: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl Default for WebsocketMessageAssembler {
+    fn default() -> Self {
+        WebsocketMessageAssembler::new()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl WebsocketMessageAssembler {
+    /// Create new instance.
+    #[inline]
+    pub const fn new() -> Self {
+        WebsocketMessageAssembler {
+            decoder: WebsocketMessageDecoder::new(),
+            message: Vec::new(),
+            control: Vec::new(),
+            max_message_size: usize::MAX,
+        }
+    }
+
+    /// Limit total payload length of a data message. Exceeding it fails `add_data`
+    /// with [`MessageDecoderError::MessageTooLarge`] before the excess is buffered.
+    #[inline]
+    pub fn set_max_message_size(&mut self, max_message_size: usize) {
+        self.max_message_size = max_message_size;
+    }
+
+    /// Currently active limit of data message size.
+    #[inline]
+    pub const fn max_message_size(&self) -> usize {
+        self.max_message_size
+    }
+
+    /// Add some bytes to the assembler and return completed message, if any.
+    ///
+    /// Input buffer needs to be mutable because it is also used to transform (unmask)
+    /// payload content chunks in-place.
+    pub fn add_data(
+        &mut self,
+        data: &mut [u8],
+    ) -> Result<WebsocketMessageAssemblerAddDataResult, MessageDecoderError> {
+        let mut offset = 0;
+        loop {
+            let buf = &mut data[offset..];
+            let ret = self.decoder.add_data(buf)?;
+            let payload = &buf[..ret.consumed_bytes];
+            offset += ret.consumed_bytes;
+            let message = match ret.event {
+                None => {
+                    if ret.consumed_bytes == 0 {
+                        return Ok(WebsocketMessageAssemblerAddDataResult {
+                            consumed_bytes: offset,
+                            message: None,
+                        });
+                    }
+                    None
+                }
+                Some(WebsocketMessageEvent::MessageStart { .. }) => {
+                    self.message.clear();
+                    None
+                }
+                Some(WebsocketMessageEvent::MessageChunk { .. }) => {
+                    if self.message.len() + payload.len() > self.max_message_size {
+                        self.message = Vec::new();
+                        return Err(MessageDecoderError::MessageTooLarge);
+                    }
+                    self.message.extend_from_slice(payload);
+                    None
+                }
+                Some(WebsocketMessageEvent::MessageEnd { opcode }) => Some(WebsocketMessage {
+                    opcode,
+                    payload: core::mem::take(&mut self.message),
+                }),
+                Some(WebsocketMessageEvent::Control(WebsocketFrameEvent::Start { .. })) => {
+                    self.control.clear();
+                    None
+                }
+                Some(WebsocketMessageEvent::Control(WebsocketFrameEvent::PayloadChunk {
+                    ..
+                })) => {
+                    self.control.extend_from_slice(payload);
+                    None
+                }
+                Some(WebsocketMessageEvent::Control(WebsocketFrameEvent::End {
+                    frame_info,
+                    ..
+                })) => Some(WebsocketMessage {
+                    opcode: frame_info.opcode,
+                    payload: core::mem::take(&mut self.control),
+                }),
+            };
+            if message.is_some() {
+                return Ok(WebsocketMessageAssemblerAddDataResult {
+                    consumed_bytes: offset,
+                    message,
+                });
+            }
+        }
+    }
+
+    /// There is no incomplete WebSocket frame or message at this moment and EOF is valid here.
+    #[inline]
+    pub fn eof_valid(&self) -> bool {
+        self.decoder.eof_valid()
+    }
+}
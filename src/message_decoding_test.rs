@@ -0,0 +1,97 @@
+use std::vec::Vec;
+
+use std::vec;
+
+use super::*;
+
+extern crate std;
+
+use pretty_assertions::assert_eq;
+
+use crate::test_util::decode_events;
+
+fn decode(input: &[u8], max_chunk_size : Option<usize>) -> Result<(Vec<u8>, Vec<WebsocketMessageEvent>), MessageDecoderError> {
+    let mut payload = Vec::new();
+    let mut events = Vec::new();
+    let mut d  = WebsocketMessageDecoder::new();
+
+    let mcs = max_chunk_size.unwrap_or(input.len().max(1));
+    for (ev, chunk) in decode_events(&mut d, input, mcs)? {
+        payload.extend_from_slice(&chunk);
+        // Collapse consecutive chunks to make results independent from chunking
+        if matches!(ev, WebsocketMessageEvent::MessageChunk { .. }) && events.last() == Some(&ev) {
+            continue;
+        }
+        events.push(ev);
+    }
+    Ok((payload, events))
+}
+
+#[test]
+fn message_simple() {
+    for mcs in [None, Some(1), Some(2), Some(3)] {
+        assert_eq!(decode(b"\x81\x05\x48\x65\x6c\x6c\x6f", mcs), Ok(((*b"Hello").into(), vec![
+            WebsocketMessageEvent::MessageStart { opcode: Opcode::Text },
+            WebsocketMessageEvent::MessageChunk { opcode: Opcode::Text },
+            WebsocketMessageEvent::MessageEnd { opcode: Opcode::Text },
+        ])));
+    }
+}
+
+#[test]
+fn message_fragmented_with_ping() {
+    let input = b"\x02\x03\x48\x65\x6c\x89\x01\x50\x00\x00\x80\x82\x37\xfa\x21\x3d\x5b\x95";
+    for mcs in [None, Some(1), Some(2), Some(5)] {
+        let ping_fi = FrameInfo { opcode: Opcode::Ping, payload_length: 1, mask: None, fin: true, reserved: 0 };
+        assert_eq!(decode(input, mcs), Ok(((*b"HelPlo").into(), vec![
+            WebsocketMessageEvent::MessageStart { opcode: Opcode::Binary },
+            WebsocketMessageEvent::MessageChunk { opcode: Opcode::Binary },
+            WebsocketMessageEvent::Control(WebsocketFrameEvent::Start { frame_info: ping_fi, original_opcode: Opcode::Ping }),
+            WebsocketMessageEvent::Control(WebsocketFrameEvent::PayloadChunk { original_opcode: Opcode::Ping }),
+            WebsocketMessageEvent::Control(WebsocketFrameEvent::End { frame_info: ping_fi, original_opcode: Opcode::Ping }),
+            WebsocketMessageEvent::MessageChunk { opcode: Opcode::Binary },
+            WebsocketMessageEvent::MessageEnd { opcode: Opcode::Binary },
+        ])));
+    }
+}
+
+#[test]
+fn message_orphan_continuation() {
+    assert_eq!(decode(b"\x80\x00", None), Err(MessageDecoderError::OrphanContinuation));
+}
+
+#[test]
+fn message_unfinished() {
+    assert_eq!(decode(b"\x01\x00\x81\x00", None), Err(MessageDecoderError::UnfinishedMessage));
+}
+
+#[cfg(feature="alloc")]
+#[test]
+fn assembler() {
+    let input = b"\x01\x03\x48\x65\x6c\x89\x01\x50\x80\x02\x6c\x6f\x82\x00";
+    let mut a = WebsocketMessageAssembler::new();
+    let messages: Vec<_> = decode_events(&mut a, input, usize::MAX).unwrap().into_iter().map(|(message, _)| message).collect();
+    assert!(a.eof_valid());
+    assert_eq!(messages, vec![
+        WebsocketMessage { opcode: Opcode::Ping, payload: b"P".to_vec() },
+        WebsocketMessage::text("Hello"),
+        WebsocketMessage::binary(b"".to_vec()),
+    ]);
+}
+
+#[cfg(feature="alloc")]
+#[test]
+fn assembler_max_message_size() {
+    let input = b"\x01\x03\x48\x65\x6c\x89\x01\x50\x80\x02\x6c\x6f";
+    for mcs in [1, 100] {
+        let mut a = WebsocketMessageAssembler::new();
+        a.set_max_message_size(5);
+        assert_eq!(a.max_message_size(), 5);
+        let messages = decode_events(&mut a, input, mcs).unwrap();
+        assert_eq!(messages.last().unwrap().0, WebsocketMessage::text("Hello"));
+
+        let mut a = WebsocketMessageAssembler::new();
+        a.set_max_message_size(4);
+        assert_eq!(decode_events(&mut a, input, mcs), Err(MessageDecoderError::MessageTooLarge));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{
+    FrameInfo, MaskingKeySource, Opcode, PayloadLength, Role, WebsocketFrameWriter,
+    WebsocketFrameWriterResult,
+};
+
+/// Error from [`WebsocketMessageWriter`] when a call would produce an invalid sequence of frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MessageWriterError {
+    /// Previous message is not finished yet.
+    MessageInProgress,
+    /// A data frame is partially written, so a control frame cannot be started now.
+    FrameInProgress,
+    /// Opcode is not a data opcode (when starting a message) or not a control opcode (when writing a control frame).
+    /// [`Opcode::Continuation`] is never accepted, as it is chosen automatically.
+    InvalidOpcode,
+    /// Control frame payload is longer than [`crate::MAX_CONTROL_PAYLOAD_LENGTH`].
+    ControlPayloadTooLong,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+enum State {
+    /// No message in progress.
+    #[default]
+    Idle,
+    /// Message started, but no frames of it were emitted yet.
+    Starting,
+    /// Some frames of the message are emitted, next one should be [`Opcode::Continuation`].
+    Continuing,
+    /// Final frame of the message is being written.
+    Finishing,
+}
+
+/// A WebSocket message writer, splitting outgoing messages of arbitrary or unknown length into frames.
+///
+/// It is built on [`WebsocketFrameWriter`]: headers and (masked) payload are copied into user-supplied output buffers.
+/// Each data frame carries at most `max_frame_payload` bytes. First frame of a message uses the opcode given to
+/// [`WebsocketMessageWriter::start_message`], subsequent ones use [`Opcode::Continuation`] and only the last one has `fin` set.
+///
+/// Frame length is chosen from the input available when the frame starts, so if message length is not known in advance,
+/// just supply chunks as they come: each of them gets flushed as one or more frames,
+/// and final empty `fin` frame is emitted when the message is finished.
+///
+/// Control frames (e.g. [`Opcode::Pong`]) can be injected between frames of a message using [`WebsocketMessageWriter::write_control`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WebsocketMessageWriter {
+    writer: WebsocketFrameWriter,
+    max_frame_payload: PayloadLength,
+    opcode: Opcode,
+    state: State,
+    control_in_progress: bool,
+}
+
+impl WebsocketMessageWriter {
+    /// Create new instance, emitting unmasked frames with payload no larger than `max_frame_payload` bytes.
+    pub const fn new(max_frame_payload: PayloadLength) -> Self {
+        WebsocketMessageWriter::from_writer(WebsocketFrameWriter::new(), max_frame_payload)
+    }
+
+    /// Create new instance which knows which side of the connection it is on, masking frames if it is [`Role::Client`].
+    pub const fn with_role(role: Role, max_frame_payload: PayloadLength) -> Self {
+        WebsocketMessageWriter::from_writer(
+            WebsocketFrameWriter::with_role(role),
+            max_frame_payload,
+        )
+    }
+
+    const fn from_writer(writer: WebsocketFrameWriter, max_frame_payload: PayloadLength) -> Self {
+        WebsocketMessageWriter {
+            writer,
+            max_frame_payload,
+            opcode: Opcode::Binary,
+            state: State::Idle,
+            control_in_progress: false,
+        }
+    }
+
+    /// Change maximum payload length of subsequent data frames.
+    #[inline]
+    pub fn set_max_frame_payload(&mut self, max_frame_payload: PayloadLength) {
+        self.max_frame_payload = max_frame_payload;
+    }
+
+    /// Begin a new message with given opcode, typically [`Opcode::Text`] or [`Opcode::Binary`].
+    ///
+    /// Fails if previous message is not finished (see [`WebsocketMessageWriter::message_finished`])
+    /// or `opcode` is not a data opcode.
+    pub fn start_message(&mut self, opcode: Opcode) -> Result<(), MessageWriterError> {
+        if !self.message_finished() {
+            return Err(MessageWriterError::MessageInProgress);
+        }
+        if !opcode.is_data() || opcode == Opcode::Continuation {
+            return Err(MessageWriterError::InvalidOpcode);
+        }
+        self.opcode = opcode;
+        self.state = State::Starting;
+        Ok(())
+    }
+
+    fn start_data_frame(
+        &mut self,
+        input_len: usize,
+        last: bool,
+        key_source: &mut impl MaskingKeySource,
+    ) {
+        let max = self.max_frame_payload.max(1);
+        let (payload_length, fin) = match PayloadLength::try_from(input_len) {
+            Ok(len) if len <= max => (len, last),
+            _ => (max, false),
+        };
+        let opcode = if self.state == State::Starting {
+            self.opcode
+        } else {
+            Opcode::Continuation
+        };
+        let frame_info = FrameInfo {
+            opcode,
+            payload_length,
+            mask: None,
+            fin,
+            reserved: 0,
+        };
+        self.writer
+            .start_frame_with_key_source(&frame_info, key_source);
+        self.state = if fin {
+            State::Finishing
+        } else {
+            State::Continuing
+        };
+    }
+
+    /// Write payload of current message from `input` into `output`, emitting frame header when starting a new frame.
+    ///
+    /// Each call writes at most up to the end of current frame, so control frames can be injected between calls
+    /// when [`WebsocketMessageWriter::frame_finished`] is true.
+    ///
+    /// Set `last` if `input` contains all remaining content of the message. Keep calling with the unconsumed part of `input`
+    /// until all of it is consumed and (when `last` is set) until [`WebsocketMessageWriter::message_finished`] is true.
+    ///
+    /// Does nothing if there is no message in progress or a control frame is being written.
+    pub fn write(
+        &mut self,
+        input: &[u8],
+        last: bool,
+        output: &mut [u8],
+        key_source: &mut impl MaskingKeySource,
+    ) -> WebsocketFrameWriterResult {
+        if self.control_in_progress {
+            return WebsocketFrameWriterResult {
+                consumed_bytes: 0,
+                produced_bytes: 0,
+            };
+        }
+        if self.writer.frame_finished() {
+            match self.state {
+                State::Starting | State::Continuing if !input.is_empty() || last => {
+                    self.start_data_frame(input.len(), last, key_source);
+                }
+                _ => {
+                    if self.state == State::Finishing {
+                        self.state = State::Idle;
+                    }
+                    return WebsocketFrameWriterResult {
+                        consumed_bytes: 0,
+                        produced_bytes: 0,
+                    };
+                }
+            }
+        }
+        self.writer.write(input, output)
+    }
+
+    /// Write a control frame (e.g. [`Opcode::Ping`]) with given payload into `output`.
+    ///
+    /// Control frames can only be started between frames, so it fails with [`MessageWriterError::FrameInProgress`]
+    /// if a data frame is partially written. Otherwise keep calling it with the same opcode and unconsumed part of `payload`
+    /// until [`WebsocketMessageWriter::frame_finished`] is true.
+    ///
+    /// When starting a frame, `opcode` must be a control opcode and `payload` must not be longer
+    /// than [`crate::MAX_CONTROL_PAYLOAD_LENGTH`]; nothing is written otherwise.
+    pub fn write_control(
+        &mut self,
+        opcode: Opcode,
+        payload: &[u8],
+        output: &mut [u8],
+        key_source: &mut impl MaskingKeySource,
+    ) -> Result<WebsocketFrameWriterResult, MessageWriterError> {
+        if !self.control_in_progress {
+            if !self.writer.frame_finished() {
+                return Err(MessageWriterError::FrameInProgress);
+            }
+            if !opcode.is_control() {
+                return Err(MessageWriterError::InvalidOpcode);
+            }
+            if payload.len() > crate::MAX_CONTROL_PAYLOAD_LENGTH as usize {
+                return Err(MessageWriterError::ControlPayloadTooLong);
+            }
+            let frame_info = FrameInfo {
+                opcode,
+                payload_length: payload.len() as PayloadLength,
+                mask: None,
+                fin: true,
+                reserved: 0,
+            };
+            self.writer
+                .start_frame_with_key_source(&frame_info, key_source);
+            self.control_in_progress = true;
+        }
+        let ret = self.writer.write(payload, output);
+        if self.writer.frame_finished() {
+            self.control_in_progress = false;
+        }
+        Ok(ret)
+    }
+
+    /// There is no partially written frame, so a control frame can be started now.
+    #[inline]
+    pub const fn frame_finished(&self) -> bool {
+        self.writer.frame_finished()
+    }
+
+    /// There is no message in progress, so a new one can be started.
+    #[inline]
+    pub fn message_finished(&self) -> bool {
+        match self.state {
+            State::Idle => true,
+            State::Finishing => self.writer.frame_finished(),
+            State::Starting | State::Continuing => false,
+        }
+    }
+}
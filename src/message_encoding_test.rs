@@ -0,0 +1,161 @@
+use super::*;
+
+extern crate std;
+
+use std::vec::Vec;
+
+use pretty_assertions::assert_eq;
+
+use crate::test_util::frames;
+
+fn write_message(
+    w: &mut WebsocketMessageWriter,
+    chunks: &[&[u8]],
+    out_size: usize,
+    out: &mut Vec<u8>,
+) {
+    let mut buf = std::vec![0u8; out_size];
+    let mut keys = || [1, 2, 3, 4];
+    for (i, chunk) in chunks.iter().enumerate() {
+        let last = i + 1 == chunks.len();
+        let mut chunk = *chunk;
+        loop {
+            let r = w.write(chunk, last, &mut buf, &mut keys);
+            out.extend_from_slice(&buf[..r.produced_bytes]);
+            chunk = &chunk[r.consumed_bytes..];
+            if chunk.is_empty() && (!last || w.message_finished()) {
+                break;
+            }
+        }
+    }
+}
+
+#[test]
+fn known_length() {
+    for out_size in [1, 3, 100] {
+        let mut w = WebsocketMessageWriter::new(4);
+        let mut out = Vec::new();
+        w.start_message(Opcode::Text).unwrap();
+        write_message(&mut w, &[b"Hello, world"], out_size, &mut out);
+        assert!(w.message_finished());
+        w.start_message(Opcode::Binary).unwrap();
+        write_message(&mut w, &[b"abc"], out_size, &mut out);
+        assert_eq!(
+            frames(out),
+            std::vec![
+                (Opcode::Text, false, false, b"Hell".to_vec()),
+                (Opcode::Continuation, false, false, b"o, w".to_vec()),
+                (Opcode::Continuation, true, false, b"orld".to_vec()),
+                (Opcode::Binary, true, false, b"abc".to_vec()),
+            ]
+        );
+    }
+}
+
+#[test]
+fn unknown_length() {
+    let mut w = WebsocketMessageWriter::with_role(Role::Client, 4);
+    let mut out = Vec::new();
+    w.start_message(Opcode::Binary).unwrap();
+    write_message(&mut w, &[b"abcdef", b"", b"gh", b""], 5, &mut out);
+    assert!(w.message_finished());
+    w.start_message(Opcode::Text).unwrap();
+    write_message(&mut w, &[b""], 5, &mut out);
+    assert_eq!(
+        frames(out),
+        std::vec![
+            (Opcode::Binary, false, true, b"abcd".to_vec()),
+            (Opcode::Continuation, false, true, b"ef".to_vec()),
+            (Opcode::Continuation, false, true, b"gh".to_vec()),
+            (Opcode::Continuation, true, true, b"".to_vec()),
+            (Opcode::Text, true, true, b"".to_vec()),
+        ]
+    );
+}
+
+#[test]
+fn control_injection() {
+    let mut w = WebsocketMessageWriter::new(4);
+    let mut keys = || [0; 4];
+    let mut out = Vec::new();
+    let mut buf = [0u8; 16];
+    w.start_message(Opcode::Binary).unwrap();
+    let r = w.write(b"abcdefgh", false, &mut buf[..3], &mut keys);
+    assert_eq!(r.consumed_bytes, 1);
+    out.extend_from_slice(&buf[..r.produced_bytes]);
+    assert!(!w.frame_finished());
+    assert_eq!(
+        w.write_control(Opcode::Ping, b"p", &mut buf, &mut keys),
+        Err(MessageWriterError::FrameInProgress)
+    );
+
+    let r = w.write(b"bcdefgh", false, &mut buf[..3], &mut keys);
+    assert_eq!(r.consumed_bytes, 3);
+    out.extend_from_slice(&buf[..r.produced_bytes]);
+    assert!(w.frame_finished());
+
+    let r = w
+        .write_control(Opcode::Pong, b"pong", &mut buf[..4], &mut keys)
+        .unwrap();
+    out.extend_from_slice(&buf[..r.produced_bytes]);
+    assert_eq!(r.consumed_bytes, 2);
+    let r = w.write(b"efgh", true, &mut buf, &mut keys);
+    assert_eq!(r.produced_bytes, 0);
+    let r = w
+        .write_control(Opcode::Pong, b"ng", &mut buf, &mut keys)
+        .unwrap();
+    out.extend_from_slice(&buf[..r.produced_bytes]);
+    assert!(w.frame_finished());
+    assert!(!w.message_finished());
+
+    let r = w.write(b"efgh", true, &mut buf, &mut keys);
+    assert_eq!(r.consumed_bytes, 4);
+    out.extend_from_slice(&buf[..r.produced_bytes]);
+    assert!(w.message_finished());
+
+    assert_eq!(
+        frames(out),
+        std::vec![
+            (Opcode::Binary, false, false, b"abcd".to_vec()),
+            (Opcode::Pong, true, false, b"pong".to_vec()),
+            (Opcode::Continuation, true, false, b"efgh".to_vec()),
+        ]
+    );
+}
+
+#[test]
+fn misuse_is_rejected() {
+    let mut w = WebsocketMessageWriter::new(4);
+    let mut keys = || [0; 4];
+    let mut buf = [0u8; 256];
+    assert_eq!(
+        w.start_message(Opcode::Continuation),
+        Err(MessageWriterError::InvalidOpcode)
+    );
+    assert_eq!(
+        w.start_message(Opcode::Ping),
+        Err(MessageWriterError::InvalidOpcode)
+    );
+    assert_eq!(
+        w.write_control(Opcode::Text, b"", &mut buf, &mut keys),
+        Err(MessageWriterError::InvalidOpcode)
+    );
+    assert_eq!(
+        w.write_control(Opcode::Ping, &[0; 126], &mut buf, &mut keys),
+        Err(MessageWriterError::ControlPayloadTooLong)
+    );
+    assert!(w.frame_finished());
+    let r = w
+        .write_control(Opcode::Ping, &[0; 125], &mut buf, &mut keys)
+        .unwrap();
+    assert_eq!(r.consumed_bytes, 125);
+
+    w.start_message(Opcode::Binary).unwrap();
+    assert_eq!(
+        w.start_message(Opcode::Text),
+        Err(MessageWriterError::MessageInProgress)
+    );
+    let r = w.write(b"ab", true, &mut buf, &mut keys);
+    assert_eq!(r.consumed_bytes, 2);
+    w.start_message(Opcode::Text).unwrap();
+}
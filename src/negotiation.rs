@@ -0,0 +1,289 @@
+use core::fmt::{self, Write};
+
+use tinyvec::ArrayVec;
+
+use crate::handshake::HeadWriter;
+use crate::HandshakeError;
+
+fn is_tchar(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c)
+}
+
+fn is_token(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(is_tchar)
+}
+
+fn skip_ows(s: &str) -> &str {
+    s.trim_start_matches([' ', '\t'])
+}
+
+/// Split a token from the beginning of `s`.
+fn token(s: &str) -> Option<(&str, &str)> {
+    let n = s.bytes().take_while(|c| is_tchar(*c)).count();
+    if n == 0 {
+        return None;
+    }
+    Some(s.split_at(n))
+}
+
+/// Split `name[=value]` from the beginning of `s`. Leading whitespace should be already skipped.
+fn param(s: &str) -> Option<(ExtensionParam<'_>, &str)> {
+    let (name, rest) = token(s)?;
+    let Some(rest) = skip_ows(rest).strip_prefix('=') else {
+        return Some((ExtensionParam { name, value: None }, rest));
+    };
+    let rest = skip_ows(rest);
+    let (value, rest) = if let Some(quoted) = rest.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        let value = &quoted[..end];
+        if !is_token(value) {
+            return None;
+        }
+        (value, &quoted[end + 1..])
+    } else {
+        token(rest)?
+    };
+    Some((
+        ExtensionParam {
+            name,
+            value: Some(value),
+        },
+        rest,
+    ))
+}
+
+/// Split an extension offer from the beginning of `s`, returning the offer and text after it (including the comma).
+fn offer(s: &str) -> Option<(ExtensionOffer<'_>, &str)> {
+    let (name, params_start) = token(s)?;
+    let mut rest = params_start;
+    while let Some(p) = skip_ows(rest).strip_prefix(';') {
+        rest = param(skip_ows(p))?.1;
+    }
+    let params = &params_start[..(params_start.len() - rest.len())];
+    let rest = skip_ows(rest);
+    if !rest.is_empty() && !rest.starts_with(',') {
+        return None;
+    }
+    Some((ExtensionOffer { name, params }, rest))
+}
+
+/// One parameter of an extension in `Sec-WebSocket-Extensions` header, e.g. `client_max_window_bits=10`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct ExtensionParam<'a> {
+    /// Parameter name.
+    pub name: &'a str,
+    /// Parameter value, without quotes. `None` if the parameter has no value.
+    pub value: Option<&'a str>,
+}
+
+/// One entry of `Sec-WebSocket-Extensions` header: extension name and its parameters.
+///
+/// [`fmt::Display`] implementation formats it back in a normalized form, so it can be echoed in server's response.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct ExtensionOffer<'a> {
+    /// Extension name, e.g. `permessage-deflate`.
+    pub name: &'a str,
+    /// Already validated text of parameters, e.g. `; client_max_window_bits`.
+    params: &'a str,
+}
+
+impl<'a> ExtensionOffer<'a> {
+    /// Iterate parameters of this extension, in order of appearance.
+    pub fn params(&self) -> ExtensionParams<'a> {
+        ExtensionParams { rest: self.params }
+    }
+
+    /// Get the first parameter with the given name.
+    pub fn param(&self, name: &str) -> Option<ExtensionParam<'a>> {
+        self.params().find(|p| p.name == name)
+    }
+}
+
+impl<'a> fmt::Display for ExtensionOffer<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)?;
+        for p in self.params() {
+            match p.value {
+                Some(v) => write!(f, "; {}={}", p.name, v)?,
+                None => write!(f, "; {}", p.name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Iterator over parameters of an [`ExtensionOffer`].
+#[derive(Debug, Clone)]
+pub struct ExtensionParams<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for ExtensionParams<'a> {
+    type Item = ExtensionParam<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let p = skip_ows(skip_ows(self.rest).strip_prefix(';')?);
+        let (ret, rest) = param(p)?;
+        self.rest = rest;
+        Some(ret)
+    }
+}
+
+/// Parser of `Sec-WebSocket-Extensions` header value: an iterator over comma-separated [`ExtensionOffer`]s.
+///
+/// Yields [`HandshakeError::InvalidNegotiationHeader`] (and stops) if the header does not follow RFC 6455 grammar.
+/// Quoted parameter values must be tokens, as required by RFC 6455; backslash escapes are not supported.
+#[derive(Debug, Clone)]
+pub struct ExtensionOffers<'a> {
+    rest: &'a str,
+}
+
+impl<'a> ExtensionOffers<'a> {
+    /// Start parsing a header value.
+    #[inline]
+    pub const fn parse(header: &'a str) -> Self {
+        ExtensionOffers { rest: header }
+    }
+}
+
+impl<'a> Iterator for ExtensionOffers<'a> {
+    type Item = Result<ExtensionOffer<'a>, HandshakeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Empty list elements are allowed by `#rule` syntax.
+        self.rest = self.rest.trim_start_matches([' ', '\t', ',']);
+        if self.rest.is_empty() {
+            return None;
+        }
+        match offer(self.rest) {
+            Some((ret, rest)) => {
+                self.rest = rest;
+                Some(Ok(ret))
+            }
+            None => {
+                self.rest = "";
+                Some(Err(HandshakeError::InvalidNegotiationHeader))
+            }
+        }
+    }
+}
+
+/// Maximum number of distinct extensions in a response, see [`negotiate_extensions`] and [`verify_extensions`].
+const MAX_ACCEPTED_EXTENSIONS: usize = 8;
+
+/// Iterate tokens of a `Sec-WebSocket-Protocol` header value.
+fn protocols(header: &str) -> impl Iterator<Item = &str> {
+    header
+        .split(',')
+        .map(|p| p.trim_matches([' ', '\t']))
+        .filter(|p| !p.is_empty())
+}
+
+/// Server side: choose a subprotocol from client's `Sec-WebSocket-Protocol` headers
+/// (e.g. [`crate::ParsedRequest::protocols`]).
+///
+/// `supported` is a list of subprotocols known to the server, in order of preference.
+/// Returns `None` if there is no header or no common subprotocol; in that case
+/// `Sec-WebSocket-Protocol` should not be included in the response.
+pub fn select_subprotocol<'a, 's>(
+    offered: impl IntoIterator<Item = &'a str> + Clone,
+    supported: &[&'s str],
+) -> Option<&'s str> {
+    supported
+        .iter()
+        .find(|s| {
+            offered
+                .clone()
+                .into_iter()
+                .flat_map(protocols)
+                .any(|o| o == **s)
+        })
+        .copied()
+}
+
+/// Client side: check that subprotocol selected by the server (from the response header) is one of those we have offered.
+pub fn verify_subprotocol<'o, 'a>(
+    offered: impl IntoIterator<Item = &'o str>,
+    selected: Option<&'a str>,
+) -> Result<Option<&'a str>, HandshakeError> {
+    let Some(selected) = selected else {
+        return Ok(None);
+    };
+    if !is_token(selected) {
+        return Err(HandshakeError::InvalidNegotiationHeader);
+    }
+    if !offered
+        .into_iter()
+        .flat_map(protocols)
+        .any(|o| o == selected)
+    {
+        return Err(HandshakeError::UnexpectedProtocol);
+    }
+    Ok(Some(selected))
+}
+
+/// Server side: go through extension offers from client's `Sec-WebSocket-Extensions` headers
+/// (e.g. [`crate::ParsedRequest::extensions`]) and build the response header value in `out`.
+///
+/// `accept` is called for each offer, in client's order of preference, and returns what to include in the response
+/// for it (e.g. the offer itself or `PerMessageDeflateParams`) or `None` to decline it.
+/// Once an offer is accepted, other offers of the same extension are skipped.
+/// At most 8 distinct extensions are accepted; offers of further extensions are declined.
+///
+/// Returns the response header value, or `None` if nothing was accepted.
+pub fn negotiate_extensions<'a, 'b, R: fmt::Display>(
+    offered: impl IntoIterator<Item = &'a str>,
+    out: &'b mut [u8],
+    mut accept: impl FnMut(&ExtensionOffer<'a>) -> Option<R>,
+) -> Result<Option<&'b str>, HandshakeError> {
+    let mut w = HeadWriter { buf: out, len: 0 };
+    let mut accepted_names = ArrayVec::<[&str; MAX_ACCEPTED_EXTENSIONS]>::new();
+    for offer in offered.into_iter().flat_map(ExtensionOffers::parse) {
+        let offer = offer?;
+        if accepted_names.contains(&offer.name) || accepted_names.len() == accepted_names.capacity()
+        {
+            continue;
+        }
+        if let Some(response) = accept(&offer) {
+            if w.len > 0 {
+                w.put(b", ")?;
+            }
+            write!(w, "{response}").map_err(|_| HandshakeError::BufferTooSmall)?;
+            accepted_names.push(offer.name);
+        }
+    }
+    let HeadWriter { buf, len } = w;
+    if len == 0 {
+        return Ok(None);
+    }
+    core::str::from_utf8(&buf[..len])
+        .map(Some)
+        .map_err(|_| HandshakeError::InvalidHeaderValue)
+}
+
+/// Client side: check that every extension in server's `Sec-WebSocket-Extensions` response headers
+/// was offered by us and is accepted only once.
+///
+/// Response with more than 8 distinct extensions is rejected with [`HandshakeError::UnexpectedExtension`].
+pub fn verify_extensions<'o, 'a>(
+    offered: impl IntoIterator<Item = &'o str> + Clone,
+    accepted: impl IntoIterator<Item = &'a str>,
+) -> Result<(), HandshakeError> {
+    let mut accepted_names = ArrayVec::<[&str; MAX_ACCEPTED_EXTENSIONS]>::new();
+    for ext in accepted.into_iter().flat_map(ExtensionOffers::parse) {
+        let ext = ext?;
+        let offered_by_us = offered
+            .clone()
+            .into_iter()
+            .flat_map(ExtensionOffers::parse)
+            .filter_map(Result::ok)
+            .any(|o| o.name == ext.name);
+        if !offered_by_us || accepted_names.contains(&ext.name) {
+            return Err(HandshakeError::UnexpectedExtension);
+        }
+        if accepted_names.try_push(ext.name).is_some() {
+            return Err(HandshakeError::UnexpectedExtension);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,205 @@
+extern crate std;
+
+use super::*;
+
+use std::format;
+use std::vec::Vec;
+
+use pretty_assertions::assert_eq;
+
+fn offers(header: &str) -> Result<Vec<(&str, Vec<ExtensionParam<'_>>)>, HandshakeError> {
+    ExtensionOffers::parse(header)
+        .map(|o| o.map(|o| (o.name, o.params().collect())))
+        .collect()
+}
+
+#[test]
+fn extension_header_parsing() {
+    assert_eq!(offers(""), Ok(Vec::new()));
+    assert_eq!(
+        offers("foo, bar; baz=2, permessage-deflate ; client_max_window_bits;server_max_window_bits = \"10\" ,, qux"),
+        Ok(std::vec![
+            ("foo", Vec::new()),
+            ("bar", std::vec![ExtensionParam { name: "baz", value: Some("2") }]),
+            (
+                "permessage-deflate",
+                std::vec![
+                    ExtensionParam { name: "client_max_window_bits", value: None },
+                    ExtensionParam { name: "server_max_window_bits", value: Some("10") },
+                ]
+            ),
+            ("qux", Vec::new()),
+        ])
+    );
+
+    for bad in [
+        "foo bar",
+        "foo;",
+        "foo; =1",
+        "foo; a=",
+        "foo; a=\"unterminated",
+        "foo; a=\"two words\"",
+        "foo; a=\"esc\\aped\"",
+        "foo; a=b c",
+        "\"foo\"",
+        "foo=1",
+    ] {
+        assert_eq!(
+            offers(bad),
+            Err(HandshakeError::InvalidNegotiationHeader),
+            "{bad}"
+        );
+    }
+
+    let offer = ExtensionOffers::parse("foo;a; b=\"1\"")
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(format!("{offer}"), "foo; a; b=1");
+    assert_eq!(offer.param("b").unwrap().value, Some("1"));
+    assert_eq!(offer.param("c"), None);
+}
+
+#[test]
+fn subprotocol_selection() {
+    let offered = Some("chat, superchat ,v2.json");
+    assert_eq!(
+        select_subprotocol(offered, &["v2.json", "chat"]),
+        Some("v2.json")
+    );
+    assert_eq!(select_subprotocol(offered, &["mqtt"]), None);
+    assert_eq!(select_subprotocol(None, &["chat"]), None);
+    assert_eq!(select_subprotocol(Some("superchat"), &["chat"]), None);
+    assert_eq!(
+        select_subprotocol(["chat", "v2.json"], &["v2.json"]),
+        Some("v2.json")
+    );
+
+    assert_eq!(
+        verify_subprotocol(offered, Some("superchat")),
+        Ok(Some("superchat"))
+    );
+    assert_eq!(verify_subprotocol(offered, None), Ok(None));
+    assert_eq!(
+        verify_subprotocol(offered, Some("mqtt")),
+        Err(HandshakeError::UnexpectedProtocol)
+    );
+    assert_eq!(
+        verify_subprotocol(None, Some("chat")),
+        Err(HandshakeError::UnexpectedProtocol)
+    );
+    assert_eq!(
+        verify_subprotocol(offered, Some("chat, superchat")),
+        Err(HandshakeError::InvalidNegotiationHeader)
+    );
+}
+
+#[test]
+fn extension_negotiation() {
+    let offered = Some("foo; x=1, bar, foo, baz; y");
+    let mut buf = [0u8; 64];
+    let mut seen = Vec::new();
+    let response = negotiate_extensions(offered, &mut buf, |o| {
+        seen.push(o.name);
+        (o.name != "bar").then_some(*o)
+    })
+    .unwrap();
+    assert_eq!(response, Some("foo; x=1, baz; y"));
+    assert_eq!(seen, ["foo", "bar", "baz"]);
+
+    assert_eq!(
+        negotiate_extensions(offered, &mut buf, |_| None::<ExtensionOffer>),
+        Ok(None)
+    );
+    assert_eq!(negotiate_extensions(None, &mut buf, |o| Some(*o)), Ok(None));
+    assert_eq!(
+        negotiate_extensions(offered, &mut buf[..10], |o| Some(*o)),
+        Err(HandshakeError::BufferTooSmall)
+    );
+    assert_eq!(
+        negotiate_extensions(Some("foo bar"), &mut buf, |o| Some(*o)),
+        Err(HandshakeError::InvalidNegotiationHeader)
+    );
+
+    assert_eq!(verify_extensions(offered, Some("baz, foo; x=2")), Ok(()));
+    assert_eq!(verify_extensions(offered, None), Ok(()));
+    assert_eq!(
+        verify_extensions(offered, Some("qux")),
+        Err(HandshakeError::UnexpectedExtension)
+    );
+    assert_eq!(
+        verify_extensions(offered, Some("foo, foo")),
+        Err(HandshakeError::UnexpectedExtension)
+    );
+    assert_eq!(
+        verify_extensions(None, Some("foo")),
+        Err(HandshakeError::UnexpectedExtension)
+    );
+    assert_eq!(
+        verify_extensions(offered, Some("foo;")),
+        Err(HandshakeError::InvalidNegotiationHeader)
+    );
+
+    // Lists split over several headers are considered as a whole.
+    let offered = ["foo, bar", "baz"];
+    assert_eq!(
+        negotiate_extensions(offered, &mut buf, |o| Some(*o)),
+        Ok(Some("foo, bar, baz"))
+    );
+    assert_eq!(verify_extensions(offered, ["foo", "baz"]), Ok(()));
+    assert_eq!(
+        verify_extensions(offered, ["foo", "foo"]),
+        Err(HandshakeError::UnexpectedExtension)
+    );
+    assert_eq!(
+        verify_extensions(offered, ["foo", "qux"]),
+        Err(HandshakeError::UnexpectedExtension)
+    );
+
+    let request = b"GET / HTTP/1.1\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\
+Sec-WebSocket-Extensions: foo\r\nSec-WebSocket-Extensions: bar\r\n\r\n";
+    let request = crate::ParsedRequest::parse(request).unwrap().unwrap().value;
+    assert_eq!(
+        negotiate_extensions(request.extensions.iter(), &mut buf, |o| Some(*o)),
+        Ok(Some("foo, bar"))
+    );
+
+    let many = "a, b, c, d, e, f, g, h, i";
+    let mut buf = [0u8; 64];
+    assert_eq!(
+        negotiate_extensions(Some(many), &mut buf, |o| Some(*o)),
+        Ok(Some("a, b, c, d, e, f, g, h"))
+    );
+    assert_eq!(
+        verify_extensions(Some(many), Some(many)),
+        Err(HandshakeError::UnexpectedExtension)
+    );
+}
+
+#[cfg(feature = "permessage_deflate")]
+#[test]
+fn extension_negotiation_permessage_deflate() {
+    use crate::PerMessageDeflateParams;
+
+    let offer = "x-webkit-deflate-frame, permessage-deflate; server_max_window_bits=10, permessage-deflate; client_max_window_bits; server_no_context_takeover";
+    let mut buf = [0u8; 128];
+    let response =
+        negotiate_extensions(Some(offer), &mut buf, PerMessageDeflateParams::accept_offer)
+            .unwrap()
+            .unwrap();
+    assert_eq!(response, "permessage-deflate; server_no_context_takeover");
+    assert_eq!(verify_extensions(Some(offer), Some(response)), Ok(()));
+    let accepted = PerMessageDeflateParams::from_response(response)
+        .unwrap()
+        .unwrap();
+    assert!(accepted.server_no_context_takeover);
+    assert_eq!(
+        negotiate_extensions(
+            Some("permessage-deflate; server_max_window_bits=9, permessage-deflate; bogus"),
+            &mut buf,
+            PerMessageDeflateParams::accept_offer
+        ),
+        Ok(None)
+    );
+}
@@ -0,0 +1,459 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+
+use miniz_oxide::deflate::core::CompressorOxide;
+use miniz_oxide::deflate::CompressionLevel;
+use miniz_oxide::inflate::stream::InflateState;
+use miniz_oxide::{DataFormat, MZError, MZFlush, MZStatus};
+
+use crate::{
+    CloseCode, Extension, ExtensionOffer, ExtensionOffers, ExtensionParam, FrameInfo, Opcode,
+    PayloadLength, Role, RSV1,
+};
+
+/// Name of the extension, as used in `Sec-WebSocket-Extensions` header.
+pub const PERMESSAGE_DEFLATE: &str = "permessage-deflate";
+
+/// Bytes which are stripped from the end of each compressed message and appended back before decompressing it.
+const DEFLATE_TAIL: [u8; 4] = [0x00, 0x00, 0xFF, 0xFF];
+
+/// Window size we use for compression. Other values cannot be honoured.
+const OUR_WINDOW_BITS: u8 = 15;
+
+/// Error from permessage-deflate negotiation or processing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PerMessageDeflateError {
+    /// Extension parameters are unknown, duplicated, malformed or cannot be honoured.
+    InvalidParameters,
+    /// RSV1 bit is set on a continuation or control frame.
+    UnexpectedRsv1,
+    /// Peer sent payload that cannot be decompressed.
+    InvalidCompressedData,
+    /// Compressor reported an error.
+    CompressionFailed,
+}
+
+impl PerMessageDeflateError {
+    /// Status code to be sent in a close frame when failing the connection due to this error.
+    pub const fn close_code(&self) -> CloseCode {
+        match self {
+            PerMessageDeflateError::InvalidCompressedData => CloseCode::InvalidPayload,
+            PerMessageDeflateError::CompressionFailed => CloseCode::InternalError,
+            _ => CloseCode::ProtocolError,
+        }
+    }
+}
+
+/// Negotiated (or offered) parameters of permessage-deflate extension, as per RFC 7692.
+///
+/// [`fmt::Display`] implementation formats a `Sec-WebSocket-Extensions` header value, e.g.
+/// `permessage-deflate; client_no_context_takeover`, suitable both for a client's offer
+/// and for server's response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PerMessageDeflateParams {
+    /// Server resets its compression context after each message.
+    pub server_no_context_takeover: bool,
+    /// Client resets its compression context after each message.
+    pub client_no_context_takeover: bool,
+    /// Limit of the server's LZ77 window size (8 to 15). `None` means no limit.
+    pub server_max_window_bits: Option<u8>,
+    /// Limit of the client's LZ77 window size (8 to 15). `None` means no limit.
+    pub client_max_window_bits: Option<u8>,
+}
+
+impl PerMessageDeflateParams {
+    /// Interpret a single `permessage-deflate` entry of `Sec-WebSocket-Extensions` header (offer or response).
+    ///
+    /// `client_max_window_bits` without a value is reported as `Some(15)`.
+    pub fn from_offer(offer: &ExtensionOffer) -> Result<Self, PerMessageDeflateError> {
+        if offer.name != PERMESSAGE_DEFLATE {
+            return Err(PerMessageDeflateError::InvalidParameters);
+        }
+        let mut ret = PerMessageDeflateParams::default();
+        for ExtensionParam { name, value } in offer.params() {
+            match name {
+                "server_no_context_takeover"
+                    if value.is_none() && !ret.server_no_context_takeover =>
+                {
+                    ret.server_no_context_takeover = true;
+                }
+                "client_no_context_takeover"
+                    if value.is_none() && !ret.client_no_context_takeover =>
+                {
+                    ret.client_no_context_takeover = true;
+                }
+                "server_max_window_bits" if ret.server_max_window_bits.is_none() => {
+                    ret.server_max_window_bits = Some(parse_window_bits(
+                        value.ok_or(PerMessageDeflateError::InvalidParameters)?,
+                    )?);
+                }
+                "client_max_window_bits" if ret.client_max_window_bits.is_none() => {
+                    ret.client_max_window_bits = Some(match value {
+                        Some(v) => parse_window_bits(v)?,
+                        None => OUR_WINDOW_BITS,
+                    });
+                }
+                _ => return Err(PerMessageDeflateError::InvalidParameters),
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Server side: decide on a client's offer, e.g. from [`crate::negotiate_extensions`] callback.
+    ///
+    /// Returns parameters to be used for the connection and sent back to the client (using [`fmt::Display`]),
+    /// or `None` if the offer should be declined. Offers which limit server's window size are declined,
+    /// as the compressor only supports full 15-bit window.
+    pub fn accept_offer(offer: &ExtensionOffer) -> Option<Self> {
+        let offer = PerMessageDeflateParams::from_offer(offer).ok()?;
+        if offer.server_max_window_bits.unwrap_or(OUR_WINDOW_BITS) != OUR_WINDOW_BITS {
+            return None;
+        }
+        Some(PerMessageDeflateParams {
+            client_max_window_bits: None,
+            ..offer
+        })
+    }
+
+    /// Client side: interpret server's `Sec-WebSocket-Extensions` response header.
+    ///
+    /// Returns `Ok(None)` if server has not enabled permessage-deflate.
+    /// Fails if parameters are malformed or limit client's window size, which the compressor cannot honour.
+    pub fn from_response(extensions_header: &str) -> Result<Option<Self>, PerMessageDeflateError> {
+        let mut params = None;
+        for entry in ExtensionOffers::parse(extensions_header) {
+            let entry = entry.map_err(|_| PerMessageDeflateError::InvalidParameters)?;
+            if entry.name != PERMESSAGE_DEFLATE {
+                continue;
+            }
+            if params.is_some() {
+                return Err(PerMessageDeflateError::InvalidParameters);
+            }
+            params = Some(PerMessageDeflateParams::from_offer(&entry)?);
+        }
+        if let Some(p) = params {
+            if p.client_max_window_bits.unwrap_or(OUR_WINDOW_BITS) != OUR_WINDOW_BITS {
+                return Err(PerMessageDeflateError::InvalidParameters);
+            }
+        }
+        Ok(params)
+    }
+}
+
+fn parse_window_bits(value: &str) -> Result<u8, PerMessageDeflateError> {
+    if value.is_empty() || value.len() > 2 || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(PerMessageDeflateError::InvalidParameters);
+    }
+    match value.parse() {
+        Ok(x @ 8..=15) => Ok(x),
+        _ => Err(PerMessageDeflateError::InvalidParameters),
+    }
+}
+
+impl fmt::Display for PerMessageDeflateParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(PERMESSAGE_DEFLATE)?;
+        if self.server_no_context_takeover {
+            f.write_str("; server_no_context_takeover")?;
+        }
+        if self.client_no_context_takeover {
+            f.write_str("; client_no_context_takeover")?;
+        }
+        if let Some(x) = self.server_max_window_bits {
+            write!(f, "; server_max_window_bits={x}")?;
+        }
+        if let Some(x) = self.client_max_window_bits {
+            write!(f, "; client_max_window_bits={x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of [`PerMessageDeflate`]'s compression and decompression methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DeflateStreamResult {
+    /// Number of bytes taken from the input buffer.
+    pub consumed_bytes: usize,
+    /// Number of bytes written to the output buffer.
+    pub produced_bytes: usize,
+    /// For `*_finish` methods, indicates that the message is complete and no more output is pending.
+    pub finished: bool,
+}
+
+/// Sans-IO permessage-deflate (RFC 7692) compressor and decompressor for one WebSocket connection.
+///
+/// Incoming side: call [`PerMessageDeflate::incoming_frame`] for each [`crate::WebsocketFrameEvent::Start`]
+/// to learn whether the message is compressed, then feed unmasked payload of
+/// [`crate::WebsocketFrameEvent::PayloadChunk`]s into [`PerMessageDeflate::decompress`] and, at the end of the
+/// message, call [`PerMessageDeflate::decompress_finish`] until it reports `finished`.
+///
+/// Register it with [`crate::ValidatingFrameDecoder::allow_extension`] to allow RSV1 bit in incoming frames.
+/// The validator then skips UTF-8 checks of compressed text messages, so validate the decompressed output yourself.
+///
+/// Outgoing side: compress message data with [`PerMessageDeflate::compress`] and [`PerMessageDeflate::compress_finish`],
+/// and send the produced bytes in frames with headers from [`PerMessageDeflate::outgoing_frame_info`],
+/// which sets RSV1 only on the first frame of a message.
+///
+/// Output buffers must not be empty. Decompressor may hold some output internally; it gets
+/// emitted by subsequent calls, so keep calling while output buffer gets filled entirely.
+///
+/// Requires `permessage_deflate` crate feature.
+pub struct PerMessageDeflate {
+    inflater: Box<InflateState>,
+    deflater: Box<CompressorOxide>,
+    reset_inflater: bool,
+    reset_deflater: bool,
+    receiving_compressed: bool,
+    inflate_ended: bool,
+    tail_consumed: usize,
+    sending_message: bool,
+    flushed: Vec<u8>,
+    flushed_offset: Option<usize>,
+}
+
+impl fmt::Debug for PerMessageDeflate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PerMessageDeflate")
+            .field("reset_inflater", &self.reset_inflater)
+            .field("reset_deflater", &self.reset_deflater)
+            .field("receiving_compressed", &self.receiving_compressed)
+            .field("sending_message", &self.sending_message)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Claims RSV1 on the first frame of data messages only. Payload is not transformed in place,
+/// use [`PerMessageDeflate::decompress`] and [`PerMessageDeflate::compress`] instead.
+impl Extension for PerMessageDeflate {
+    fn reserved_bits(&self) -> u8 {
+        RSV1
+    }
+
+    fn reserved_bits_for_opcode(&self, opcode: Opcode) -> u8 {
+        match opcode {
+            Opcode::Text | Opcode::Binary => RSV1,
+            _ => 0,
+        }
+    }
+}
+
+impl PerMessageDeflate {
+    /// Create compression state for negotiated `params`. `role` is our side of the connection.
+    pub fn new(params: PerMessageDeflateParams, role: Role) -> Self {
+        let (ours, peers) = match role {
+            Role::Client => (
+                params.client_no_context_takeover,
+                params.server_no_context_takeover,
+            ),
+            Role::Server => (
+                params.server_no_context_takeover,
+                params.client_no_context_takeover,
+            ),
+        };
+        PerMessageDeflate {
+            inflater: InflateState::new_boxed(DataFormat::Raw),
+            deflater: Box::new(CompressorOxide::with_format_and_level(
+                DataFormat::Raw,
+                CompressionLevel::DefaultLevel,
+            )),
+            reset_inflater: peers,
+            reset_deflater: ours,
+            receiving_compressed: false,
+            inflate_ended: false,
+            tail_consumed: 0,
+            sending_message: false,
+            flushed: Vec::new(),
+            flushed_offset: None,
+        }
+    }
+
+    /// Observe header of an incoming frame. Returns whether its payload should be decompressed.
+    ///
+    /// Fails if RSV1 is set on a frame other than the first frame of a data message.
+    pub fn incoming_frame(
+        &mut self,
+        frame_info: &FrameInfo,
+    ) -> Result<bool, PerMessageDeflateError> {
+        let rsv1 = frame_info.reserved & RSV1 != 0;
+        match frame_info.opcode {
+            Opcode::Continuation if !rsv1 => Ok(self.receiving_compressed),
+            Opcode::Continuation => Err(PerMessageDeflateError::UnexpectedRsv1),
+            x if x.is_data() => {
+                self.receiving_compressed = rsv1;
+                Ok(rsv1)
+            }
+            _ if rsv1 => Err(PerMessageDeflateError::UnexpectedRsv1),
+            _ => Ok(false),
+        }
+    }
+
+    fn inflate(
+        &mut self,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(usize, usize), PerMessageDeflateError> {
+        let r =
+            miniz_oxide::inflate::stream::inflate(&mut self.inflater, input, output, MZFlush::None);
+        match r.status {
+            Ok(MZStatus::StreamEnd) => self.inflate_ended = true,
+            Ok(_) | Err(MZError::Buf) => (),
+            Err(_) => return Err(PerMessageDeflateError::InvalidCompressedData),
+        }
+        Ok((r.bytes_consumed, r.bytes_written))
+    }
+
+    /// Decompress a piece of compressed message payload.
+    pub fn decompress(
+        &mut self,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<DeflateStreamResult, PerMessageDeflateError> {
+        if self.inflate_ended {
+            // Like zlib, ignore anything after a final DEFLATE block.
+            return Ok(DeflateStreamResult {
+                consumed_bytes: input.len(),
+                produced_bytes: 0,
+                finished: false,
+            });
+        }
+        let (consumed_bytes, produced_bytes) = self.inflate(input, output)?;
+        Ok(DeflateStreamResult {
+            consumed_bytes,
+            produced_bytes,
+            finished: false,
+        })
+    }
+
+    /// Finish decompressing a message, flushing remaining output. Call repeatedly until `finished` is reported.
+    pub fn decompress_finish(
+        &mut self,
+        output: &mut [u8],
+    ) -> Result<DeflateStreamResult, PerMessageDeflateError> {
+        let mut produced_bytes = 0;
+        if !self.inflate_ended {
+            let (consumed, produced) = self.inflate(&DEFLATE_TAIL[self.tail_consumed..], output)?;
+            self.tail_consumed += consumed;
+            produced_bytes = produced;
+            if !self.inflate_ended && (self.tail_consumed < DEFLATE_TAIL.len() || produced > 0) {
+                return Ok(DeflateStreamResult {
+                    consumed_bytes: 0,
+                    produced_bytes,
+                    finished: false,
+                });
+            }
+        }
+        if self.inflate_ended || self.reset_inflater {
+            self.inflater.reset(DataFormat::Raw);
+        }
+        self.inflate_ended = false;
+        self.tail_consumed = 0;
+        self.receiving_compressed = false;
+        Ok(DeflateStreamResult {
+            consumed_bytes: 0,
+            produced_bytes,
+            finished: true,
+        })
+    }
+
+    /// Compress a piece of outgoing message.
+    pub fn compress(
+        &mut self,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<DeflateStreamResult, PerMessageDeflateError> {
+        let r =
+            miniz_oxide::deflate::stream::deflate(&mut self.deflater, input, output, MZFlush::None);
+        match r.status {
+            Ok(_) | Err(MZError::Buf) => (),
+            Err(_) => return Err(PerMessageDeflateError::CompressionFailed),
+        }
+        Ok(DeflateStreamResult {
+            consumed_bytes: r.bytes_consumed,
+            produced_bytes: r.bytes_written,
+            finished: false,
+        })
+    }
+
+    /// Finish compressing a message, flushing remaining output. Call repeatedly until `finished` is reported.
+    ///
+    /// Trailing `00 00 FF FF` bytes are stripped, as required by RFC 7692.
+    pub fn compress_finish(
+        &mut self,
+        output: &mut [u8],
+    ) -> Result<DeflateStreamResult, PerMessageDeflateError> {
+        let offset = match self.flushed_offset {
+            Some(x) => x,
+            None => {
+                self.flush_deflater()?;
+                0
+            }
+        };
+        let n = output.len().min(self.flushed.len() - offset);
+        output[..n].copy_from_slice(&self.flushed[offset..(offset + n)]);
+        let finished = offset + n == self.flushed.len();
+        if finished {
+            self.flushed.clear();
+            self.flushed_offset = None;
+            if self.reset_deflater {
+                self.deflater.reset();
+            }
+        } else {
+            self.flushed_offset = Some(offset + n);
+        }
+        Ok(DeflateStreamResult {
+            consumed_bytes: 0,
+            produced_bytes: n,
+            finished,
+        })
+    }
+
+    fn flush_deflater(&mut self) -> Result<(), PerMessageDeflateError> {
+        const CHUNK: usize = 256;
+        loop {
+            let len = self.flushed.len();
+            self.flushed.resize(len + CHUNK, 0);
+            let r = miniz_oxide::deflate::stream::deflate(
+                &mut self.deflater,
+                &[],
+                &mut self.flushed[len..],
+                MZFlush::Sync,
+            );
+            self.flushed.truncate(len + r.bytes_written);
+            if r.status.is_err() {
+                return Err(PerMessageDeflateError::CompressionFailed);
+            }
+            if r.bytes_written < CHUNK {
+                break;
+            }
+        }
+        if !self.flushed.ends_with(&DEFLATE_TAIL) {
+            return Err(PerMessageDeflateError::CompressionFailed);
+        }
+        self.flushed
+            .truncate(self.flushed.len() - DEFLATE_TAIL.len());
+        self.flushed_offset = Some(0);
+        Ok(())
+    }
+
+    /// Prepare header for the next outgoing frame of a compressed message.
+    ///
+    /// `opcode` should be [`Opcode::Text`] or [`Opcode::Binary`]; it is replaced with [`Opcode::Continuation`]
+    /// for non-first frames of a message. RSV1 is set on the first frame only. Mask is not set.
+    pub fn outgoing_frame_info(
+        &mut self,
+        opcode: Opcode,
+        payload_length: PayloadLength,
+        fin: bool,
+    ) -> FrameInfo {
+        let first = !self.sending_message;
+        self.sending_message = !fin;
+        FrameInfo {
+            opcode: if first { opcode } else { Opcode::Continuation },
+            payload_length,
+            mask: None,
+            fin,
+            reserved: if first { RSV1 } else { 0 },
+        }
+    }
+}
@@ -0,0 +1,336 @@
+use super::*;
+
+extern crate std;
+
+use std::format;
+use std::vec::Vec;
+
+use pretty_assertions::assert_eq;
+
+use crate::test_util::{decode_events, frame_with_reserved};
+
+fn first_offer(header: &str) -> ExtensionOffer<'_> {
+    ExtensionOffers::parse(header).next().unwrap().unwrap()
+}
+
+fn parse(header: &str) -> Result<PerMessageDeflateParams, PerMessageDeflateError> {
+    PerMessageDeflateParams::from_offer(&first_offer(header))
+}
+
+#[test]
+fn params_parse_and_format() {
+    let p =
+        parse("permessage-deflate; server_no_context_takeover ; client_max_window_bits").unwrap();
+    assert_eq!(
+        p,
+        PerMessageDeflateParams {
+            server_no_context_takeover: true,
+            client_no_context_takeover: false,
+            server_max_window_bits: None,
+            client_max_window_bits: Some(15),
+        }
+    );
+    let p = parse("permessage-deflate; server_max_window_bits=\"10\"").unwrap();
+    assert_eq!(p.server_max_window_bits, Some(10));
+    assert_eq!(
+        format!("{p}"),
+        "permessage-deflate; server_max_window_bits=10"
+    );
+
+    for bad in [
+        "x-webkit-deflate-frame",
+        "permessage-deflate; foo",
+        "permessage-deflate; server_max_window_bits",
+        "permessage-deflate; server_max_window_bits=7",
+        "permessage-deflate; client_max_window_bits=16",
+        "permessage-deflate; server_no_context_takeover; server_no_context_takeover",
+        "permessage-deflate; client_no_context_takeover=1",
+    ] {
+        assert_eq!(
+            parse(bad),
+            Err(PerMessageDeflateError::InvalidParameters),
+            "{bad}"
+        );
+    }
+}
+
+#[test]
+fn negotiation() {
+    let offer = "x-webkit-deflate-frame, permessage-deflate; server_max_window_bits=10, permessage-deflate; client_max_window_bits; server_no_context_takeover";
+    let accepted = ExtensionOffers::parse(offer)
+        .filter_map(Result::ok)
+        .find_map(|o| PerMessageDeflateParams::accept_offer(&o))
+        .unwrap();
+    assert_eq!(
+        format!("{accepted}"),
+        "permessage-deflate; server_no_context_takeover"
+    );
+    assert_eq!(
+        PerMessageDeflateParams::accept_offer(&first_offer(
+            "permessage-deflate; server_max_window_bits=9"
+        )),
+        None
+    );
+    assert_eq!(
+        PerMessageDeflateParams::accept_offer(&first_offer("permessage-deflate; bogus")),
+        None
+    );
+    assert_eq!(
+        PerMessageDeflateParams::accept_offer(&first_offer("foo")),
+        None
+    );
+
+    assert_eq!(PerMessageDeflateParams::from_response("foo"), Ok(None));
+    assert_eq!(
+        PerMessageDeflateParams::from_response(&format!("{accepted}")),
+        Ok(Some(accepted))
+    );
+    assert_eq!(
+        PerMessageDeflateParams::from_response("permessage-deflate; client_max_window_bits=12"),
+        Err(PerMessageDeflateError::InvalidParameters)
+    );
+    assert_eq!(
+        PerMessageDeflateParams::from_response("permessage-deflate, permessage-deflate"),
+        Err(PerMessageDeflateError::InvalidParameters)
+    );
+    assert_eq!(
+        PerMessageDeflateParams::from_response("permessage-deflate; x=\"a b\""),
+        Err(PerMessageDeflateError::InvalidParameters)
+    );
+}
+
+fn decompress_all(d: &mut PerMessageDeflate, payload: &[u8], out_size: usize) -> Vec<u8> {
+    let mut ret = Vec::new();
+    let mut buf = std::vec![0u8; out_size];
+    for chunk in payload.chunks(3) {
+        let mut chunk = chunk;
+        loop {
+            let r = d.decompress(chunk, &mut buf).unwrap();
+            ret.extend_from_slice(&buf[..r.produced_bytes]);
+            chunk = &chunk[r.consumed_bytes..];
+            if chunk.is_empty() && r.produced_bytes < buf.len() {
+                break;
+            }
+        }
+    }
+    loop {
+        let r = d.decompress_finish(&mut buf).unwrap();
+        ret.extend_from_slice(&buf[..r.produced_bytes]);
+        if r.finished {
+            break;
+        }
+    }
+    ret
+}
+
+/// Compress a message, returning encoded frames, each holding output of one compression call.
+fn compress_message(
+    c: &mut PerMessageDeflate,
+    opcode: Opcode,
+    message: &[u8],
+    out_size: usize,
+) -> Vec<u8> {
+    let mut frames = Vec::new();
+    let mut buf = std::vec![0u8; out_size];
+    let mut emit = |c: &mut PerMessageDeflate, payload: &[u8], fin: bool| {
+        let fi = c.outgoing_frame_info(opcode, payload.len() as PayloadLength, fin);
+        frames.extend_from_slice(&encode_frame_header(&fi));
+        frames.extend_from_slice(payload);
+    };
+    let mut input = message;
+    while !input.is_empty() {
+        let r = c.compress(input, &mut buf).unwrap();
+        input = &input[r.consumed_bytes..];
+        if r.produced_bytes > 0 {
+            emit(c, &buf[..r.produced_bytes], false);
+        }
+    }
+    loop {
+        let r = c.compress_finish(&mut buf).unwrap();
+        if r.finished || r.produced_bytes > 0 {
+            emit(c, &buf[..r.produced_bytes], r.finished);
+        }
+        if r.finished {
+            break;
+        }
+    }
+    frames
+}
+
+/// Decode frames of a single message, returning decompressed content.
+fn receive_message(d: &mut PerMessageDeflate, frames: Vec<u8>, out_size: usize) -> Vec<u8> {
+    let mut payload = Vec::new();
+    let mut first = true;
+    for (event, chunk) in
+        decode_events(&mut WebsocketFrameDecoder::new(), &frames, usize::MAX).unwrap()
+    {
+        match event {
+            WebsocketFrameEvent::Start { frame_info, .. } => {
+                assert_eq!(frame_info.reserved != 0, first);
+                assert!(d.incoming_frame(&frame_info).unwrap());
+                first = false;
+            }
+            WebsocketFrameEvent::PayloadChunk { .. } => payload.extend_from_slice(&chunk),
+            WebsocketFrameEvent::End { .. } => (),
+        }
+    }
+    decompress_all(d, &payload, out_size)
+}
+
+#[test]
+fn rfc7692_examples() {
+    let mut d = PerMessageDeflate::new(PerMessageDeflateParams::default(), Role::Client);
+    let fi = FrameInfo {
+        opcode: Opcode::Text,
+        payload_length: 7,
+        mask: None,
+        fin: true,
+        reserved: 4,
+    };
+    assert!(d.incoming_frame(&fi).unwrap());
+    let hello = [0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00];
+    assert_eq!(decompress_all(&mut d, &hello, 2), b"Hello");
+    // Second message referencing the first one, using context takeover.
+    assert_eq!(
+        decompress_all(&mut d, &[0xf2, 0x00, 0x11, 0x00, 0x00], 100),
+        b"Hello"
+    );
+    // Final block with BFINAL bit set.
+    assert_eq!(
+        decompress_all(
+            &mut d,
+            &[0xf3, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00, 0x00],
+            100
+        ),
+        b"Hello"
+    );
+    assert_eq!(decompress_all(&mut d, &hello, 100), b"Hello");
+
+    assert_eq!(
+        d.decompress(&[0xff, 0xff, 0xff], &mut [0; 16]),
+        Err(PerMessageDeflateError::InvalidCompressedData)
+    );
+}
+
+#[test]
+fn rsv1_rules() {
+    let mut d = PerMessageDeflate::new(PerMessageDeflateParams::default(), Role::Server);
+    let mut fi = FrameInfo {
+        opcode: Opcode::Binary,
+        payload_length: 1,
+        mask: None,
+        fin: false,
+        reserved: 0,
+    };
+    assert_eq!(d.incoming_frame(&fi), Ok(false));
+    fi.opcode = Opcode::Continuation;
+    assert_eq!(d.incoming_frame(&fi), Ok(false));
+    fi.reserved = 4;
+    assert_eq!(
+        d.incoming_frame(&fi),
+        Err(PerMessageDeflateError::UnexpectedRsv1)
+    );
+    fi.opcode = Opcode::Ping;
+    assert_eq!(
+        d.incoming_frame(&fi),
+        Err(PerMessageDeflateError::UnexpectedRsv1)
+    );
+    fi.opcode = Opcode::Text;
+    assert_eq!(d.incoming_frame(&fi), Ok(true));
+    fi.opcode = Opcode::Continuation;
+    fi.reserved = 0;
+    assert_eq!(d.incoming_frame(&fi), Ok(true));
+}
+
+#[test]
+fn roundtrip() {
+    for no_context_takeover in [false, true] {
+        let params = PerMessageDeflateParams {
+            server_no_context_takeover: no_context_takeover,
+            client_no_context_takeover: no_context_takeover,
+            ..Default::default()
+        };
+        let mut client = PerMessageDeflate::new(params, Role::Client);
+        let mut server = PerMessageDeflate::new(params, Role::Server);
+        let mut sizes = Vec::new();
+        for (i, out_size) in [(0usize, 7usize), (1, 1), (2, 4096), (3, 13), (4, 1)] {
+            let message: Vec<u8> = (0..(i * 5000))
+                .map(|x| ((x / 7) % 50) as u8 + b'A')
+                .collect();
+            let frames = compress_message(&mut client, Opcode::Binary, &message, out_size);
+            sizes.push(frames.len());
+            assert_eq!(
+                receive_message(&mut server, frames, out_size.max(2)),
+                message
+            );
+            let frames = compress_message(&mut server, Opcode::Binary, &message, out_size);
+            assert_eq!(receive_message(&mut client, frames, 1), message);
+        }
+        assert!(sizes[4] < 4000);
+    }
+}
+
+#[test]
+fn validator_rsv1_rules() {
+    let ext = PerMessageDeflate::new(PerMessageDeflateParams::default(), Role::Client);
+    for (frames, expected) in [
+        (
+            std::vec![frame_with_reserved(Opcode::Text, RSV1, true, b"")],
+            None,
+        ),
+        (
+            std::vec![
+                frame_with_reserved(Opcode::Binary, RSV1, false, b""),
+                frame_with_reserved(Opcode::Continuation, 0, true, b"")
+            ],
+            None,
+        ),
+        (
+            std::vec![
+                frame_with_reserved(Opcode::Binary, RSV1, false, b""),
+                frame_with_reserved(Opcode::Continuation, RSV1, true, b"")
+            ],
+            Some(ProtocolViolation::ReservedBits),
+        ),
+        (
+            std::vec![frame_with_reserved(Opcode::Ping, RSV1, true, b"")],
+            Some(ProtocolViolation::ReservedBits),
+        ),
+        (
+            std::vec![frame_with_reserved(Opcode::Text, RSV2, true, b"")],
+            Some(ProtocolViolation::ReservedBits),
+        ),
+    ] {
+        let mut v = ValidatingFrameDecoder::new();
+        v.allow_extension(&ext);
+        let error = decode_events(&mut v, &frames.concat(), usize::MAX).err();
+        assert_eq!(error, expected, "{frames:?}");
+    }
+}
+
+#[test]
+fn validator_skips_utf8_of_compressed_text() {
+    let mut c = PerMessageDeflate::new(PerMessageDeflateParams::default(), Role::Server);
+    let mut d = PerMessageDeflate::new(PerMessageDeflateParams::default(), Role::Client);
+    let message = "Привет, мир! ".repeat(50);
+    let frames = compress_message(&mut c, Opcode::Text, message.as_bytes(), 64);
+
+    let mut v = ValidatingFrameDecoder::new();
+    v.allow_extension(&c);
+    let mut payload = Vec::new();
+    for (event, chunk) in decode_events(&mut v, &frames, usize::MAX).unwrap() {
+        match event {
+            WebsocketFrameEvent::Start { frame_info, .. } => {
+                assert!(d.incoming_frame(&frame_info).unwrap());
+            }
+            WebsocketFrameEvent::PayloadChunk { .. } => payload.extend_from_slice(&chunk),
+            WebsocketFrameEvent::End { .. } => (),
+        }
+    }
+    let decompressed = decompress_all(&mut d, &payload, 100);
+    assert_eq!(core::str::from_utf8(&decompressed), Ok(&message[..]));
+
+    // Uncompressed text messages are still validated.
+    let error = decode_events(&mut v, b"\x81\x02\xc3\x28", usize::MAX).unwrap_err();
+    assert!(matches!(error, ProtocolViolation::InvalidUtf8(_)));
+}
@@ -0,0 +1,118 @@
+use crate::{
+    frame_encoding::encode_frame_header, masking, FrameInfo, Opcode, PayloadLength,
+    WebsocketFrameEvent, MAX_CONTROL_PAYLOAD_LENGTH,
+};
+
+/// Maximum size of an encoded control frame: two basic header bytes, masking key and the payload.
+const MAX_CONTROL_FRAME_LENGTH: usize = 2 + 4 + MAX_CONTROL_PAYLOAD_LENGTH as usize;
+
+/// Ready-to-send [`Opcode::Pong`] frame: header and (masked, if needed) payload, as returned by [`PongResponder::take_pong`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PongFrame {
+    data: [u8; MAX_CONTROL_FRAME_LENGTH],
+    len: u8,
+}
+
+impl PongFrame {
+    /// Bytes of the whole frame, to be written to the socket.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[..self.len as usize]
+    }
+}
+
+/// Sans-IO helper that replies to [`Opcode::Ping`] frames.
+///
+/// Feed it all events from [`crate::WebsocketFrameDecoder`] (together with payload bytes for
+/// [`WebsocketFrameEvent::PayloadChunk`]) using [`PongResponder::handle_event`]. When a Ping frame
+/// is complete, a Pong reply with the same payload becomes available from [`PongResponder::take_pong`].
+/// If multiple Pings arrive before the Pong is taken, only the most recent one is replied to, as permitted by RFC 6455.
+///
+/// Pong frame is encoded without using your [`crate::WebsocketFrameEncoder`], so it can be sent between
+/// frames of an outgoing fragmented message without disturbing encoder's masking state.
+/// It must not be sent in the middle of another frame's payload though.
+///
+/// Ping payloads longer than [`MAX_CONTROL_PAYLOAD_LENGTH`] are invalid and are ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PongResponder {
+    buf: [u8; MAX_CONTROL_PAYLOAD_LENGTH as usize],
+    len: u8,
+    receiving_ping: bool,
+    pending: Option<u8>,
+    pending_buf: [u8; MAX_CONTROL_PAYLOAD_LENGTH as usize],
+}
+
+impl Default for PongResponder {
+    fn default() -> Self {
+        PongResponder::new()
+    }
+}
+
+impl PongResponder {
+    /// Create new instance.
+    #[inline]
+    pub const fn new() -> Self {
+        PongResponder {
+            buf: [0; MAX_CONTROL_PAYLOAD_LENGTH as usize],
+            len: 0,
+            receiving_ping: false,
+            pending: None,
+            pending_buf: [0; MAX_CONTROL_PAYLOAD_LENGTH as usize],
+        }
+    }
+
+    /// Observe an event from the decoder.
+    ///
+    /// `payload` should be the unmasked payload bytes for [`WebsocketFrameEvent::PayloadChunk`] events
+    /// and is ignored for other events.
+    pub fn handle_event(&mut self, event: &WebsocketFrameEvent, payload: &[u8]) {
+        match event {
+            WebsocketFrameEvent::Start { frame_info, .. } => {
+                self.receiving_ping = frame_info.opcode == Opcode::Ping
+                    && frame_info.payload_length <= MAX_CONTROL_PAYLOAD_LENGTH.into();
+                self.len = 0;
+            }
+            WebsocketFrameEvent::PayloadChunk { original_opcode: Opcode::Ping } if self.receiving_ping => {
+                let offset = self.len as usize;
+                let n = payload.len().min(self.buf.len() - offset);
+                self.buf[offset..(offset + n)].copy_from_slice(&payload[..n]);
+                self.len += n as u8;
+            }
+            WebsocketFrameEvent::End { .. } if self.receiving_ping => {
+                self.receiving_ping = false;
+                self.pending_buf = self.buf;
+                self.pending = Some(self.len);
+            }
+            _ => (),
+        }
+    }
+
+    /// Check if there is a Pong frame to be sent.
+    #[inline]
+    pub const fn pong_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Get pending Pong frame, if any. Use `Some` `mask` when you are a client.
+    pub fn take_pong(&mut self, mask: Option<[u8; 4]>) -> Option<PongFrame> {
+        let len = self.pending.take()?;
+        let payload = &mut self.pending_buf[..len as usize];
+        let header = encode_frame_header(&FrameInfo {
+            opcode: Opcode::Pong,
+            payload_length: len as PayloadLength,
+            mask,
+            fin: true,
+            reserved: 0,
+        });
+        if let Some(mask) = mask {
+            masking::apply_mask(mask, payload, 0);
+        }
+        let mut ret = PongFrame {
+            data: [0; MAX_CONTROL_FRAME_LENGTH],
+            len: (header.len() + payload.len()) as u8,
+        };
+        ret.data[..header.len()].copy_from_slice(&header);
+        ret.data[header.len()..(ret.len as usize)].copy_from_slice(payload);
+        Some(ret)
+    }
+}
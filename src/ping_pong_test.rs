@@ -0,0 +1,67 @@
+use std::vec::Vec;
+
+use super::*;
+
+extern crate std;
+
+use pretty_assertions::assert_eq;
+
+use crate::test_util::decode_events;
+
+fn feed(p: &mut PongResponder, input: &[u8], max_chunk_size: usize) {
+    let events = decode_events(&mut WebsocketFrameDecoder::new(), input, max_chunk_size).unwrap();
+    for (ev, payload) in events {
+        p.handle_event(&ev, &payload);
+    }
+}
+
+#[test]
+fn pong_unmasked() {
+    for mcs in [1, 3, 100] {
+        let mut p = PongResponder::new();
+        assert!(!p.pong_pending());
+        feed(&mut p, b"\x81\x02hi\x89\x85\x37\xfa\x21\x3d\x7f\x9f\x4d\x51\x58\x81\x00", mcs);
+        assert!(p.pong_pending());
+        assert_eq!(p.take_pong(None).unwrap().as_bytes(), b"\x8a\x05Hello");
+        assert_eq!(p.take_pong(None), None);
+    }
+}
+
+#[test]
+fn pong_masked_latest_ping_only() {
+    let mut p = PongResponder::new();
+    feed(&mut p, b"\x89\x03one\x89\x05Hello", 2);
+    assert_eq!(p.take_pong(Some(*b"\x37\xfa\x21\x3d")).unwrap().as_bytes(), b"\x8a\x85\x37\xfa\x21\x3d\x7f\x9f\x4d\x51\x58");
+}
+
+#[test]
+fn pong_interleaved_with_fragmented_message() {
+    let mut out = Vec::new();
+    let mut e = WebsocketFrameEncoder::new();
+    let mask = Some(*b"\x01\x02\x03\x04");
+
+    let mut p = PongResponder::new();
+    feed(&mut p, b"\x89\x01P", 1);
+
+    out.extend(e.start_frame(&FrameInfo { opcode: Opcode::Text, payload_length: 3, mask, fin: false, reserved: 0 }).unwrap());
+    let mut chunk = *b"Hel";
+    e.transform_frame_payload(&mut chunk);
+    out.extend(chunk);
+    out.extend_from_slice(p.take_pong(Some(*b"\x11\x22\x33\x44")).unwrap().as_bytes());
+    out.extend(e.start_frame(&FrameInfo { opcode: Opcode::Continuation, payload_length: 2, mask, fin: true, reserved: 0 }).unwrap());
+    let mut chunk = *b"lo";
+    e.transform_frame_payload(&mut chunk);
+    out.extend(chunk);
+
+    let mut text = Vec::new();
+    let mut pong = Vec::new();
+    for (ev, payload) in decode_events(&mut WebsocketMessageDecoder::new(), &out, usize::MAX).unwrap() {
+        match ev {
+            WebsocketMessageEvent::MessageChunk { .. } => text.extend_from_slice(&payload),
+            WebsocketMessageEvent::Control(WebsocketFrameEvent::PayloadChunk { .. }) => pong.extend_from_slice(&payload),
+            _ => (),
+        }
+    }
+    assert_eq!(text, b"Hello");
+    assert_eq!(pong, b"P");
+}
@@ -0,0 +1,51 @@
+/// Side of a WebSocket connection, determining masking rules.
+///
+/// Clients must mask all frames they send and servers must not mask any frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Role {
+    /// Party that initiated the connection. Sends masked frames, receives unmasked frames.
+    Client,
+    /// Party that accepted the connection. Sends unmasked frames, receives masked frames.
+    Server,
+}
+
+impl Role {
+    /// Whether frames sent by this side should be masked.
+    #[inline]
+    pub const fn masks_outgoing(&self) -> bool {
+        matches!(self, Role::Client)
+    }
+
+    /// Whether frames received by this side are expected to be masked.
+    #[inline]
+    pub const fn expects_masked_incoming(&self) -> bool {
+        matches!(self, Role::Server)
+    }
+
+    /// Other side of the connection.
+    #[inline]
+    pub const fn peer(&self) -> Role {
+        match self {
+            Role::Client => Role::Server,
+            Role::Server => Role::Client,
+        }
+    }
+}
+
+/// Source of masking keys for outgoing frames.
+///
+/// RFC 6455 requires masking keys to be unpredictable, so it should typically be backed by
+/// a cryptographically strong random number generator.
+///
+/// Implemented for closures returning `[u8; 4]`, e.g. `|| rand::random()`.
+pub trait MaskingKeySource {
+    /// Get masking key for the next frame.
+    fn next_masking_key(&mut self) -> [u8; 4];
+}
+
+impl<F: FnMut() -> [u8; 4]> MaskingKeySource for F {
+    #[inline]
+    fn next_masking_key(&mut self) -> [u8; 4] {
+        self()
+    }
+}
@@ -0,0 +1,359 @@
+use core::hash::{BuildHasher, Hasher};
+use core::ops::Range;
+
+use alloc::format;
+use alloc::vec::Vec;
+use std::collections::hash_map::RandomState;
+use std::io::{self, Read, Write};
+
+use crate::connection::{Connection, ConnectionEvent};
+use crate::{
+    ClosePayload, DecoderLimits, FrameInfo, MaskingKeySource, Opcode, PayloadLength, Role,
+    WebsocketMessage,
+};
+
+/// Size of the buffer for incoming data.
+const READ_BUFFER_SIZE: usize = 4096;
+
+/// Masking keys derived from randomly seeded SipHash of a counter.
+#[derive(Debug, Clone)]
+pub(crate) struct RandomMaskingKeys {
+    state: RandomState,
+    counter: u64,
+}
+
+impl RandomMaskingKeys {
+    pub(crate) fn new() -> Self {
+        RandomMaskingKeys {
+            state: RandomState::new(),
+            counter: 0,
+        }
+    }
+}
+
+impl MaskingKeySource for RandomMaskingKeys {
+    fn next_masking_key(&mut self) -> [u8; 4] {
+        let mut h = self.state.build_hasher();
+        h.write_u64(self.counter);
+        self.counter = self.counter.wrapping_add(1);
+        (h.finish() as u32).to_ne_bytes()
+    }
+}
+
+/// Convert outcome of a failed operation to [`io::Error`].
+pub(crate) fn io_error(kind: io::ErrorKind, e: impl core::fmt::Debug) -> io::Error {
+    io::Error::new(kind, format!("WebSocket error: {e:?}"))
+}
+
+/// A WebSocket connection over a blocking byte stream (e.g. [`std::net::TcpStream`]), after the handshake is done.
+///
+/// Requires `std` crate feature.
+///
+/// Incoming messages can be read whole using [`WebSocketStream::read_message`] or as an [`io::Read`]
+/// using [`WebSocketStream::read_message_start`]. Outgoing messages are sent with [`WebSocketStream::write_message`]
+/// or streamed as a sequence of frames using [`WebSocketStream::message_writer`].
+///
+/// Incoming frames are validated with [`crate::ValidatingFrameDecoder`]. Pings are replied to automatically
+/// and peer's Close frame is echoed back, after which reading methods return `None`.
+/// Protocol violations fail the connection with appropriate close code and are reported as [`io::ErrorKind::InvalidData`].
+#[derive(Debug)]
+pub struct WebSocketStream<S> {
+    inner: S,
+    connection: Connection<RandomMaskingKeys>,
+    buf: Vec<u8>,
+    start: usize,
+    end: usize,
+    /// Unread part of the payload chunk currently being read through [`WebSocketStreamMessageReader`].
+    pending_payload: Range<usize>,
+    in_message: bool,
+    closed: bool,
+    scratch: Vec<u8>,
+}
+
+impl<S: Read + Write> WebSocketStream<S> {
+    /// Wrap a stream, which already passed the WebSocket handshake.
+    pub fn new(inner: S, role: Role) -> Self {
+        WebSocketStream::with_initial_data(inner, role, &[])
+    }
+
+    /// Wrap a stream, which already passed the WebSocket handshake, also supplying bytes
+    /// which were read from it after the handshake.
+    pub fn with_initial_data(inner: S, role: Role, data: &[u8]) -> Self {
+        let mut buf = Vec::with_capacity(READ_BUFFER_SIZE.max(data.len()));
+        buf.extend_from_slice(data);
+        let end = buf.len();
+        buf.resize(buf.capacity(), 0);
+        WebSocketStream {
+            inner,
+            connection: Connection::new(role, RandomMaskingKeys::new()),
+            buf,
+            start: 0,
+            end,
+            pending_payload: 0..0,
+            in_message: false,
+            closed: false,
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Get next event from the socket, with range of `buf` holding payload for [`ConnectionEvent::Payload`].
+    ///
+    /// Returns `None` after the connection is closed.
+    fn next_event(&mut self) -> io::Result<Option<(ConnectionEvent, Range<usize>)>> {
+        loop {
+            if self.closed {
+                return Ok(None);
+            }
+            let ret = self
+                .connection
+                .add_data(&mut self.buf[self.start..self.end]);
+            self.flush_pending_output()?;
+            let ret = ret.map_err(|e| {
+                self.closed = true;
+                io_error(io::ErrorKind::InvalidData, e)
+            })?;
+            let range = self.start..(self.start + ret.consumed_bytes);
+            self.start += ret.consumed_bytes;
+            match ret.event {
+                Some(ConnectionEvent::Closed) => {
+                    self.closed = true;
+                    return Ok(None);
+                }
+                Some(ev) => return Ok(Some((ev, range))),
+                None if ret.consumed_bytes > 0 => continue,
+                None => (),
+            }
+            let n = self.inner.read(&mut self.buf)?;
+            self.start = 0;
+            self.end = n;
+            if n == 0 {
+                self.closed = true;
+                return match self.connection.eof() {
+                    Ok(()) => Ok(None),
+                    Err(code) => Err(io_error(io::ErrorKind::UnexpectedEof, code)),
+                };
+            }
+        }
+    }
+
+    fn flush_pending_output(&mut self) -> io::Result<()> {
+        let pending = self.connection.pending_output();
+        if !pending.is_empty() {
+            let n = pending.len();
+            self.inner.write_all(pending)?;
+            self.connection.consume_pending_output(n);
+            self.inner.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Read the next complete data message.
+    ///
+    /// Returns `None` if the connection is closed.
+    pub fn read_message(&mut self) -> io::Result<Option<WebsocketMessage>> {
+        let Some(mut reader) = self.read_message_start()? else {
+            return Ok(None);
+        };
+        let mut message = WebsocketMessage {
+            opcode: reader.opcode(),
+            payload: Vec::new(),
+        };
+        reader.read_to_end(&mut message.payload)?;
+        Ok(Some(message))
+    }
+
+    /// Wait for the next data message and return [`io::Read`] over its payload.
+    ///
+    /// Unread content of previous message is skipped. Returns `None` if the connection is closed.
+    pub fn read_message_start(
+        &mut self,
+    ) -> io::Result<Option<WebSocketStreamMessageReader<'_, S>>> {
+        self.pending_payload = 0..0;
+        loop {
+            match self.next_event()? {
+                None => return Ok(None),
+                Some((ConnectionEvent::MessageStart(opcode), _)) => {
+                    self.in_message = true;
+                    return Ok(Some(WebSocketStreamMessageReader {
+                        stream: self,
+                        opcode,
+                    }));
+                }
+                Some((ConnectionEvent::MessageEnd(_), _)) => self.in_message = false,
+                Some(_) => (),
+            }
+        }
+    }
+
+    /// Send a frame, flushing pending control frames first.
+    fn write_frame(&mut self, opcode: Opcode, payload: &[u8], fin: bool) -> io::Result<()> {
+        self.flush_pending_output()?;
+        let frame_info = FrameInfo {
+            opcode,
+            payload_length: PayloadLength::try_from(payload.len())
+                .map_err(|e| io_error(io::ErrorKind::InvalidInput, e))?,
+            mask: None,
+            fin,
+            reserved: 0,
+        };
+        let header = self
+            .connection
+            .start_frame(&frame_info)
+            .map_err(|e| io_error(io::ErrorKind::InvalidInput, e))?;
+        self.scratch.clear();
+        self.scratch.extend_from_slice(&header);
+        self.scratch.extend_from_slice(payload);
+        self.connection
+            .transform_frame_payload(&mut self.scratch[header.len()..]);
+        self.inner.write_all(&self.scratch)
+    }
+
+    /// Send a complete message in one frame.
+    pub fn write_message(&mut self, opcode: Opcode, payload: &[u8]) -> io::Result<()> {
+        self.write_frame(opcode, payload, true)?;
+        self.inner.flush()
+    }
+
+    /// Begin sending a message of unknown length. Each [`io::Write::write`] call on returned object sends one frame.
+    ///
+    /// The message should be completed with [`WebSocketStreamMessageWriter::finish`].
+    #[must_use = "the message should be completed with `WebSocketStreamMessageWriter::finish`"]
+    pub fn message_writer(&mut self, opcode: Opcode) -> WebSocketStreamMessageWriter<'_, S> {
+        WebSocketStreamMessageWriter {
+            stream: self,
+            opcode,
+            finished: false,
+        }
+    }
+
+    /// Initiate the closing handshake. Keep reading until `None` is returned to receive peer's reply.
+    pub fn close(&mut self, payload: &ClosePayload<'_>) -> io::Result<()> {
+        let payload = payload
+            .encode()
+            .map_err(|e| io_error(io::ErrorKind::InvalidInput, e))?;
+        self.write_message(Opcode::ConnectionClose, &payload)
+    }
+
+    /// Payload of the Close frame received from peer, if any.
+    pub fn peer_close(&self) -> Option<ClosePayload<'_>> {
+        self.connection.peer_close()
+    }
+
+    /// Set resource limits for incoming frames, which are unlimited by default.
+    /// Exceeding them fails the connection with [`crate::CloseCode::MessageTooBig`].
+    pub fn set_limits(&mut self, limits: DecoderLimits) {
+        self.connection.set_limits(limits)
+    }
+
+    /// Get reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get mutable reference to the underlying stream. Reading or writing it directly would likely corrupt the WebSocket connection.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Unwrap the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+/// [`io::Read`] over payload of a single incoming message, as returned by [`WebSocketStream::read_message_start`].
+///
+/// Reading returns 0 when the message ends.
+#[derive(Debug)]
+pub struct WebSocketStreamMessageReader<'a, S> {
+    stream: &'a mut WebSocketStream<S>,
+    opcode: Opcode,
+}
+
+impl<'a, S> WebSocketStreamMessageReader<'a, S> {
+    /// Opcode of this message, typically [`Opcode::Text`] or [`Opcode::Binary`].
+    #[inline]
+    pub fn opcode(&self) -> Opcode {
+        self.opcode
+    }
+}
+
+impl<'a, S: Read + Write> Read for WebSocketStreamMessageReader<'a, S> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let stream = &mut *self.stream;
+        while stream.pending_payload.is_empty() {
+            if !stream.in_message {
+                return Ok(0);
+            }
+            match stream.next_event()? {
+                Some((ConnectionEvent::Payload, range)) => stream.pending_payload = range,
+                Some((ConnectionEvent::MessageEnd(_), _)) => stream.in_message = false,
+                Some(_) => (),
+                None => {
+                    stream.in_message = false;
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+            }
+        }
+        let range = stream.pending_payload.clone();
+        let n = range.len().min(out.len());
+        out[..n].copy_from_slice(&stream.buf[range.start..(range.start + n)]);
+        stream.pending_payload.start += n;
+        Ok(n)
+    }
+}
+
+/// [`io::Write`] sending a single outgoing message as a sequence of frames, as returned by [`WebSocketStream::message_writer`].
+///
+/// The message must be completed with [`WebSocketStreamMessageWriter::finish`], which reports errors of sending
+/// the final frame. Like with [`std::io::BufWriter`], dropping the writer instead sends the final frame
+/// as a fallback, blocking in `drop` and losing any error.
+#[derive(Debug)]
+pub struct WebSocketStreamMessageWriter<'a, S: Read + Write> {
+    stream: &'a mut WebSocketStream<S>,
+    opcode: Opcode,
+    finished: bool,
+}
+
+impl<'a, S: Read + Write> WebSocketStreamMessageWriter<'a, S> {
+    fn next_opcode(&mut self) -> Opcode {
+        core::mem::replace(&mut self.opcode, Opcode::Continuation)
+    }
+
+    /// Send final frame of the message.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.finish_inner()
+    }
+
+    fn finish_inner(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        let opcode = self.next_opcode();
+        self.stream.write_frame(opcode, &[], true)?;
+        self.stream.inner.flush()
+    }
+}
+
+impl<'a, S: Read + Write> Write for WebSocketStreamMessageWriter<'a, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let opcode = self.next_opcode();
+        self.stream.write_frame(opcode, buf, false)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.inner.flush()
+    }
+}
+
+impl<'a, S: Read + Write> Drop for WebSocketStreamMessageWriter<'a, S> {
+    /// Send the final frame unless [`WebSocketStreamMessageWriter::finish`] was called. Errors are ignored.
+    fn drop(&mut self) {
+        let _ = self.finish_inner();
+    }
+}
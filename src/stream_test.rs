@@ -0,0 +1,222 @@
+use super::*;
+
+extern crate std;
+
+use std::io::{Cursor, Read, Write};
+use std::vec::Vec;
+
+use pretty_assertions::assert_eq;
+
+use crate::test_util::{close_payload, frame, frames};
+use crate::{
+    encode_frame_header, CloseCode, ClosePayload, DecoderLimits, FrameInfo, Opcode, Role,
+    WebsocketMessage,
+};
+
+/// In-memory stream: reads from prepared input, collects output.
+struct Mock {
+    input: Cursor<Vec<u8>>,
+    output: Vec<u8>,
+}
+
+impl Read for Mock {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // Deliver data in small portions to exercise buffering.
+        let n = buf.len().min(3);
+        self.input.read(&mut buf[..n])
+    }
+}
+
+impl Write for Mock {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.output.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn stream(role: Role, input: &[&[u8]]) -> WebSocketStream<Mock> {
+    WebSocketStream::new(
+        Mock {
+            input: Cursor::new(input.concat()),
+            output: Vec::new(),
+        },
+        role,
+    )
+}
+
+#[test]
+fn server_reads_messages() {
+    let m = Some([1, 2, 3, 4]);
+    let mut s = stream(
+        Role::Server,
+        &[
+            &frame(Opcode::Text, false, m, b"Hel"),
+            &frame(Opcode::Ping, true, m, b"ping"),
+            &frame(Opcode::Continuation, true, m, b"lo"),
+            &frame(Opcode::Binary, true, m, b"\x00\x01"),
+            &frame(Opcode::Binary, true, m, b"skipped"),
+            &frame(Opcode::Binary, true, m, b"0123456789"),
+            &frame(
+                Opcode::ConnectionClose,
+                true,
+                m,
+                &close_payload(CloseCode::GoingAway),
+            ),
+        ],
+    );
+    assert_eq!(
+        s.read_message().unwrap(),
+        Some(WebsocketMessage::text("Hello"))
+    );
+    assert_eq!(
+        s.read_message().unwrap(),
+        Some(WebsocketMessage::binary(&b"\x00\x01"[..]))
+    );
+    // Message left unread is skipped.
+    assert_eq!(
+        s.read_message_start().unwrap().unwrap().opcode(),
+        Opcode::Binary
+    );
+    let mut reader = s.read_message_start().unwrap().unwrap();
+    let mut buf = [0u8; 4];
+    assert_eq!(reader.read(&mut buf).unwrap(), 3);
+    assert_eq!(&buf[..3], b"012");
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, b"3456789");
+    assert_eq!(reader.read(&mut buf).unwrap(), 0);
+
+    assert_eq!(s.read_message().unwrap(), None);
+    assert_eq!(s.peer_close().unwrap().code, Some(CloseCode::GoingAway));
+    assert_eq!(s.read_message().unwrap(), None);
+    assert!(s.write_message(Opcode::Text, b"late").is_err());
+
+    assert_eq!(
+        frames(s.into_inner().output),
+        std::vec![
+            (Opcode::Pong, true, false, b"ping".to_vec()),
+            (
+                Opcode::ConnectionClose,
+                true,
+                false,
+                close_payload(CloseCode::GoingAway)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn client_writes_messages() {
+    let mut s = stream(
+        Role::Client,
+        &[&frame(
+            Opcode::ConnectionClose,
+            true,
+            None,
+            &close_payload(CloseCode::Normal),
+        )],
+    );
+    s.write_message(Opcode::Text, b"Hello").unwrap();
+    let mut w = s.message_writer(Opcode::Binary);
+    w.write_all(b"ab").unwrap();
+    w.write_all(b"").unwrap();
+    w.write_all(b"cd").unwrap();
+    w.finish().unwrap();
+    drop(s.message_writer(Opcode::Text));
+    s.close(&ClosePayload::new(CloseCode::Normal)).unwrap();
+    assert_eq!(s.read_message().unwrap(), None);
+
+    assert_eq!(
+        frames(s.into_inner().output),
+        std::vec![
+            (Opcode::Text, true, true, b"Hello".to_vec()),
+            (Opcode::Binary, false, true, b"ab".to_vec()),
+            (Opcode::Continuation, false, true, b"cd".to_vec()),
+            (Opcode::Continuation, true, true, b"".to_vec()),
+            (Opcode::Text, true, true, b"".to_vec()),
+            (
+                Opcode::ConnectionClose,
+                true,
+                true,
+                close_payload(CloseCode::Normal)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn failures() {
+    let mut s = stream(Role::Server, &[&frame(Opcode::Text, true, None, b"x")]);
+    let e = s.read_message().unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(s.read_message().unwrap(), None);
+    assert_eq!(
+        frames(s.into_inner().output),
+        std::vec![(
+            Opcode::ConnectionClose,
+            true,
+            false,
+            close_payload(CloseCode::ProtocolError)
+        )]
+    );
+
+    let mut s = stream(Role::Client, &[&frame(Opcode::Text, false, None, b"x")]);
+    let e = s.read_message().unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof);
+
+    let mut s = stream(
+        Role::Client,
+        &[&encode_frame_header(&FrameInfo {
+            opcode: Opcode::Binary,
+            payload_length: 0,
+            mask: None,
+            fin: true,
+            reserved: 0,
+        })],
+    );
+    assert_eq!(
+        s.read_message().unwrap(),
+        Some(WebsocketMessage::binary(Vec::new()))
+    );
+    assert_eq!(
+        s.read_message().unwrap_err().kind(),
+        std::io::ErrorKind::UnexpectedEof
+    );
+}
+
+#[test]
+fn limits() {
+    let mut s = stream(
+        Role::Client,
+        &[
+            &frame(Opcode::Binary, true, None, b"1234"),
+            &frame(Opcode::Binary, true, None, b"12345"),
+        ],
+    );
+    s.set_limits(DecoderLimits {
+        max_message_size: 4,
+        ..DecoderLimits::unlimited()
+    });
+    assert_eq!(
+        s.read_message().unwrap(),
+        Some(WebsocketMessage::binary(b"1234".to_vec()))
+    );
+    assert_eq!(
+        s.read_message().unwrap_err().kind(),
+        std::io::ErrorKind::InvalidData
+    );
+    assert_eq!(s.read_message().unwrap(), None);
+    assert_eq!(
+        frames(s.into_inner().output),
+        std::vec![(
+            Opcode::ConnectionClose,
+            true,
+            true,
+            close_payload(CloseCode::MessageTooBig)
+        )]
+    );
+}
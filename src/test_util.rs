@@ -0,0 +1,224 @@
+//! Helpers shared by tests.
+
+extern crate std;
+
+use std::vec::Vec;
+
+#[cfg(any(
+    feature = "std",
+    feature = "embedded_io",
+    feature = "embedded_io_async"
+))]
+use crate::{CloseCode, ClosePayload};
+use crate::{
+    FrameInfo, Opcode, PayloadLength, ProtocolViolation, ValidatingFrameDecoder,
+    WebsocketFrameDecoder, WebsocketFrameEncoder, WebsocketFrameEvent, WebsocketMessageDecoder,
+    WebsocketMessageEvent,
+};
+
+/// Encode a whole frame, masking its payload if `frame_info.mask` is set.
+/// Payload length in `frame_info` is ignored.
+fn encode_frame(frame_info: FrameInfo, payload: &[u8]) -> Vec<u8> {
+    let mut e = WebsocketFrameEncoder::new();
+    let mut ret: Vec<u8> = e
+        .start_frame(&FrameInfo {
+            payload_length: payload.len() as PayloadLength,
+            ..frame_info
+        })
+        .unwrap()
+        .to_vec();
+    let mut payload = payload.to_vec();
+    e.transform_frame_payload(&mut payload);
+    ret.extend_from_slice(&payload);
+    ret
+}
+
+/// Encode a whole frame, masking its payload if `mask` is set.
+#[cfg(any(
+    feature = "std",
+    feature = "embedded_io",
+    feature = "embedded_io_async"
+))]
+pub(crate) fn frame(opcode: Opcode, fin: bool, mask: Option<[u8; 4]>, payload: &[u8]) -> Vec<u8> {
+    encode_frame(
+        FrameInfo {
+            opcode,
+            payload_length: 0,
+            mask,
+            fin,
+            reserved: 0,
+        },
+        payload,
+    )
+}
+
+/// Encode a whole unmasked frame with given RSV bits.
+pub(crate) fn frame_with_reserved(
+    opcode: Opcode,
+    reserved: u8,
+    fin: bool,
+    payload: &[u8],
+) -> Vec<u8> {
+    encode_frame(
+        FrameInfo {
+            opcode,
+            payload_length: 0,
+            mask: None,
+            fin,
+            reserved,
+        },
+        payload,
+    )
+}
+
+/// Decode frames into `(opcode, fin, masked, payload)` tuples.
+pub(crate) fn frames(mut data: Vec<u8>) -> Vec<(Opcode, bool, bool, Vec<u8>)> {
+    let mut d = WebsocketFrameDecoder::new();
+    let mut ret = Vec::new();
+    let mut payload = Vec::new();
+    for ev in d.events(&mut data) {
+        match ev.unwrap() {
+            (WebsocketFrameEvent::PayloadChunk { .. }, chunk) => payload.extend_from_slice(chunk),
+            (WebsocketFrameEvent::End { frame_info, .. }, _) => ret.push((
+                frame_info.opcode,
+                frame_info.fin,
+                frame_info.mask.is_some(),
+                core::mem::take(&mut payload),
+            )),
+            _ => (),
+        }
+    }
+    ret
+}
+
+/// Decoder with the usual `add_data` interface, to be driven by [`decode_events`].
+pub(crate) trait AddData {
+    type Event;
+    type Error;
+
+    /// Call `add_data`, returning the event and the number of consumed bytes.
+    fn add_data(&mut self, data: &mut [u8]) -> Result<(Option<Self::Event>, usize), Self::Error>;
+
+    /// Consumed bytes of this event are payload.
+    fn has_payload(event: &Self::Event) -> bool;
+}
+
+impl AddData for WebsocketFrameDecoder {
+    type Event = WebsocketFrameEvent;
+    type Error = crate::FrameDecoderError;
+
+    fn add_data(&mut self, data: &mut [u8]) -> Result<(Option<Self::Event>, usize), Self::Error> {
+        let ret = WebsocketFrameDecoder::add_data(self, data)?;
+        Ok((ret.event, ret.consumed_bytes))
+    }
+
+    fn has_payload(event: &Self::Event) -> bool {
+        matches!(event, WebsocketFrameEvent::PayloadChunk { .. })
+    }
+}
+
+impl AddData for ValidatingFrameDecoder {
+    type Event = WebsocketFrameEvent;
+    type Error = ProtocolViolation;
+
+    fn add_data(&mut self, data: &mut [u8]) -> Result<(Option<Self::Event>, usize), Self::Error> {
+        let ret = ValidatingFrameDecoder::add_data(self, data)?;
+        Ok((ret.event, ret.consumed_bytes))
+    }
+
+    fn has_payload(event: &Self::Event) -> bool {
+        matches!(event, WebsocketFrameEvent::PayloadChunk { .. })
+    }
+}
+
+impl AddData for WebsocketMessageDecoder {
+    type Event = WebsocketMessageEvent;
+    type Error = crate::MessageDecoderError;
+
+    fn add_data(&mut self, data: &mut [u8]) -> Result<(Option<Self::Event>, usize), Self::Error> {
+        let ret = WebsocketMessageDecoder::add_data(self, data)?;
+        Ok((ret.event, ret.consumed_bytes))
+    }
+
+    fn has_payload(event: &Self::Event) -> bool {
+        matches!(
+            event,
+            WebsocketMessageEvent::MessageChunk { .. }
+                | WebsocketMessageEvent::Control(WebsocketFrameEvent::PayloadChunk { .. })
+        )
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl AddData for crate::WebsocketMessageAssembler {
+    type Event = crate::WebsocketMessage;
+    type Error = crate::MessageDecoderError;
+
+    fn add_data(&mut self, data: &mut [u8]) -> Result<(Option<Self::Event>, usize), Self::Error> {
+        let ret = crate::WebsocketMessageAssembler::add_data(self, data)?;
+        Ok((ret.message, ret.consumed_bytes))
+    }
+
+    fn has_payload(_event: &Self::Event) -> bool {
+        false
+    }
+}
+
+#[cfg(any(
+    feature = "std",
+    feature = "embedded_io",
+    feature = "embedded_io_async"
+))]
+impl<K: crate::MaskingKeySource> AddData for crate::connection::Connection<K> {
+    type Event = crate::connection::ConnectionEvent;
+    type Error = ProtocolViolation;
+
+    fn add_data(&mut self, data: &mut [u8]) -> Result<(Option<Self::Event>, usize), Self::Error> {
+        let ret = crate::connection::Connection::add_data(self, data)?;
+        Ok((ret.event, ret.consumed_bytes))
+    }
+
+    fn has_payload(event: &Self::Event) -> bool {
+        *event == crate::connection::ConnectionEvent::Payload
+    }
+}
+
+/// Event collected by [`decode_events`] with its payload bytes.
+pub(crate) type Decoded<D> = (<D as AddData>::Event, Vec<u8>);
+
+/// Feed `input` to the decoder in chunks of at most `max_chunk_size` bytes, followed by an empty buffer.
+///
+/// Each chunk is processed the way [`WebsocketFrameDecoder::events`] does it,
+/// collecting events together with their payload bytes.
+pub(crate) fn decode_events<D: AddData>(
+    d: &mut D,
+    input: &[u8],
+    max_chunk_size: usize,
+) -> Result<Vec<Decoded<D>>, D::Error> {
+    let mut input = input.to_vec();
+    let mut ret = Vec::new();
+    for mut buf in input.chunks_mut(max_chunk_size).chain([&mut [][..]]) {
+        loop {
+            let (event, consumed_bytes) = d.add_data(buf)?;
+            let (chunk, rest) = core::mem::take(&mut buf).split_at_mut(consumed_bytes);
+            buf = rest;
+            match event {
+                Some(ev) if D::has_payload(&ev) => ret.push((ev, chunk.to_vec())),
+                Some(ev) => ret.push((ev, Vec::new())),
+                None if consumed_bytes == 0 => break,
+                None => (),
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/// Payload of a Close frame with given status code and no reason.
+#[cfg(any(
+    feature = "std",
+    feature = "embedded_io",
+    feature = "embedded_io_async"
+))]
+pub(crate) fn close_payload(code: CloseCode) -> Vec<u8> {
+    ClosePayload::new(code).encode().unwrap().to_vec()
+}
@@ -0,0 +1,119 @@
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+
+use std::io;
+
+use futures_core::Stream;
+use futures_sink::Sink;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::async_stream::{AsyncTransport, AsyncWebSocket};
+use crate::{ClosePayload, DecoderLimits, Role, WebsocketMessage};
+
+#[derive(Debug)]
+struct TokioIo<S>(S);
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncTransport for TokioIo<S> {
+    fn poll_read(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let mut buf = ReadBuf::new(buf);
+        ready!(Pin::new(&mut self.0).poll_read(cx, &mut buf))?;
+        Poll::Ready(Ok(buf.filled().len()))
+    }
+
+    fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+/// A WebSocket connection over Tokio's [`AsyncRead`] + [`AsyncWrite`] (e.g. `TcpStream` or upgraded hyper connection),
+/// after the handshake is done.
+///
+/// Requires `tokio` crate feature.
+///
+/// It is a [`Stream`] of incoming data messages and a [`Sink`] of outgoing messages (each sent as a single frame).
+/// Incoming frames are validated with [`crate::ValidatingFrameDecoder`]. Pings are replied to automatically
+/// and peer's Close frame is echoed back, after which the stream ends. Closing the sink sends a Close frame
+/// with [`crate::CloseCode::Normal`] (unless one was already sent) and shuts down the writing side.
+///
+/// Wrapped stream should be `Unpin`; use `Box::pin` for other streams.
+#[derive(Debug)]
+pub struct TokioWebSocketStream<S> {
+    inner: AsyncWebSocket<TokioIo<S>>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> TokioWebSocketStream<S> {
+    /// Wrap a stream, which already passed the WebSocket handshake.
+    pub fn new(inner: S, role: Role) -> Self {
+        TokioWebSocketStream::with_initial_data(inner, role, &[])
+    }
+
+    /// Wrap a stream, which already passed the WebSocket handshake, also supplying bytes
+    /// which were read from it after the handshake (e.g. `read_buf` of hyper's `Parts`).
+    pub fn with_initial_data(inner: S, role: Role, data: &[u8]) -> Self {
+        TokioWebSocketStream {
+            inner: AsyncWebSocket::new(TokioIo(inner), role, data),
+        }
+    }
+
+    /// Payload of the Close frame received from peer, if any.
+    pub fn peer_close(&self) -> Option<ClosePayload<'_>> {
+        self.inner.peer_close()
+    }
+
+    /// Set resource limits for incoming frames, which are unlimited by default.
+    /// Exceeding them fails the connection with [`crate::CloseCode::MessageTooBig`].
+    pub fn set_limits(&mut self, limits: DecoderLimits) {
+        self.inner.set_limits(limits)
+    }
+
+    /// Get reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.inner.inner.0
+    }
+
+    /// Get mutable reference to the underlying stream. Reading or writing it directly would likely corrupt the WebSocket connection.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner.inner.0
+    }
+
+    /// Unwrap the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.inner.inner.0
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Stream for TokioWebSocketStream<S> {
+    type Item = io::Result<WebsocketMessage>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().inner.poll_next_message(cx)
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Sink<WebsocketMessage> for TokioWebSocketStream<S> {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().inner.poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: WebsocketMessage) -> io::Result<()> {
+        self.get_mut().inner.start_send(item.opcode, &item.payload)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().inner.poll_close(cx)
+    }
+}
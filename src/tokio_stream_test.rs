@@ -0,0 +1,240 @@
+use super::*;
+
+extern crate std;
+
+use core::future::poll_fn;
+use core::pin::Pin;
+use std::io;
+use std::vec::Vec;
+
+use futures_core::Stream;
+use futures_sink::Sink;
+use pretty_assertions::assert_eq;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::test_util::{close_payload, frame, frames};
+use crate::{CloseCode, Opcode};
+
+async fn next<S: AsyncRead + AsyncWrite + Unpin>(
+    ws: &mut TokioWebSocketStream<S>,
+) -> Option<io::Result<WebsocketMessage>> {
+    poll_fn(|cx| Pin::new(&mut *ws).poll_next(cx)).await
+}
+
+async fn send<S: AsyncRead + AsyncWrite + Unpin>(
+    ws: &mut TokioWebSocketStream<S>,
+    message: WebsocketMessage,
+) -> io::Result<()> {
+    poll_fn(|cx| Pin::new(&mut *ws).poll_ready(cx)).await?;
+    Pin::new(&mut *ws).start_send(message)?;
+    poll_fn(|cx| Pin::new(&mut *ws).poll_flush(cx)).await
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn tokio_client() {
+    let (client, mut server) = tokio::io::duplex(4096);
+    let mut ws = TokioWebSocketStream::with_initial_data(
+        client,
+        Role::Client,
+        &frame(Opcode::Text, true, None, b"early"),
+    );
+    let big: Vec<u8> = (0..1000u16).map(|x| x as u8).collect();
+    server
+        .write_all(
+            &[
+                frame(Opcode::Ping, true, None, b"p"),
+                frame(Opcode::Binary, true, None, &big),
+            ]
+            .concat(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        next(&mut ws).await.unwrap().unwrap(),
+        WebsocketMessage::text("early")
+    );
+    assert_eq!(
+        next(&mut ws).await.unwrap().unwrap(),
+        WebsocketMessage::binary(big)
+    );
+    send(&mut ws, WebsocketMessage::text("hi")).await.unwrap();
+
+    server
+        .write_all(&frame(
+            Opcode::ConnectionClose,
+            true,
+            None,
+            &close_payload(CloseCode::GoingAway),
+        ))
+        .await
+        .unwrap();
+    assert!(next(&mut ws).await.is_none());
+    assert_eq!(ws.peer_close().unwrap().code, Some(CloseCode::GoingAway));
+    assert!(send(&mut ws, WebsocketMessage::text("late")).await.is_err());
+    poll_fn(|cx| Pin::new(&mut ws).poll_close(cx))
+        .await
+        .unwrap();
+
+    let mut output = Vec::new();
+    server.read_to_end(&mut output).await.unwrap();
+    assert_eq!(
+        frames(output),
+        std::vec![
+            (Opcode::Pong, true, true, b"p".to_vec()),
+            (Opcode::Text, true, true, b"hi".to_vec()),
+            (
+                Opcode::ConnectionClose,
+                true,
+                true,
+                close_payload(CloseCode::GoingAway)
+            ),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn tokio_server_closes() {
+    let (server, mut client) = tokio::io::duplex(4096);
+    let mut ws = TokioWebSocketStream::new(server, Role::Server);
+    poll_fn(|cx| Pin::new(&mut ws).poll_close(cx))
+        .await
+        .unwrap();
+    client
+        .write_all(&frame(
+            Opcode::ConnectionClose,
+            true,
+            Some([1, 2, 3, 4]),
+            &close_payload(CloseCode::Normal),
+        ))
+        .await
+        .unwrap();
+    assert!(next(&mut ws).await.is_none());
+
+    let mut output = Vec::new();
+    client.read_to_end(&mut output).await.unwrap();
+    assert_eq!(
+        frames(output),
+        std::vec![(
+            Opcode::ConnectionClose,
+            true,
+            false,
+            close_payload(CloseCode::Normal)
+        )]
+    );
+
+    let (server, mut client) = tokio::io::duplex(4096);
+    let mut ws = TokioWebSocketStream::new(server, Role::Server);
+    client
+        .write_all(&frame(Opcode::Text, true, None, b"x"))
+        .await
+        .unwrap();
+    assert_eq!(
+        next(&mut ws).await.unwrap().unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+    assert!(next(&mut ws).await.is_none());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn tokio_limits() {
+    let (client, mut server) = tokio::io::duplex(4096);
+    let mut ws = TokioWebSocketStream::new(client, Role::Client);
+    ws.set_limits(DecoderLimits {
+        max_fragments: 2,
+        ..DecoderLimits::unlimited()
+    });
+    server
+        .write_all(
+            &[
+                frame(Opcode::Text, false, None, b"a"),
+                frame(Opcode::Continuation, false, None, b"b"),
+                frame(Opcode::Continuation, true, None, b"c"),
+            ]
+            .concat(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        next(&mut ws).await.unwrap().unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+    assert!(next(&mut ws).await.is_none());
+    drop(ws);
+
+    let mut output = Vec::new();
+    server.read_to_end(&mut output).await.unwrap();
+    assert_eq!(
+        frames(output),
+        std::vec![(
+            Opcode::ConnectionClose,
+            true,
+            true,
+            close_payload(CloseCode::MessageTooBig)
+        )]
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn tokio_invalid_sends() {
+    let (client, mut server) = tokio::io::duplex(4096);
+    let mut ws = TokioWebSocketStream::new(client, Role::Client);
+    for message in [
+        WebsocketMessage {
+            opcode: Opcode::Continuation,
+            payload: b"x".to_vec(),
+        },
+        WebsocketMessage {
+            opcode: Opcode::Ping,
+            payload: std::vec![0; 126],
+        },
+        WebsocketMessage {
+            opcode: Opcode::ConnectionClose,
+            payload: std::vec![0x03],
+        },
+    ] {
+        assert_eq!(
+            send(&mut ws, message).await.unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+
+    // A message can not be started before the previous one is written out.
+    Pin::new(&mut ws)
+        .start_send(WebsocketMessage::text("a"))
+        .unwrap();
+    assert_eq!(
+        Pin::new(&mut ws)
+            .start_send(WebsocketMessage::text("b"))
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::InvalidInput
+    );
+
+    // Close frame sent as a message is registered by the closing handshake.
+    let close = WebsocketMessage {
+        opcode: Opcode::ConnectionClose,
+        payload: close_payload(CloseCode::GoingAway),
+    };
+    send(&mut ws, close).await.unwrap();
+    assert!(send(&mut ws, WebsocketMessage::text("late")).await.is_err());
+    poll_fn(|cx| Pin::new(&mut ws).poll_close(cx))
+        .await
+        .unwrap();
+    drop(ws);
+
+    let mut output = Vec::new();
+    server.read_to_end(&mut output).await.unwrap();
+    assert_eq!(
+        frames(output),
+        std::vec![
+            (Opcode::Text, true, true, b"a".to_vec()),
+            (
+                Opcode::ConnectionClose,
+                true,
+                true,
+                close_payload(CloseCode::GoingAway)
+            ),
+        ]
+    );
+}
@@ -0,0 +1,156 @@
+/// Error returned by [`Utf8Validator`] when message content is not a valid UTF-8.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct Utf8Error {
+    /// Offset (from the beginning of the message) of the first byte that cannot be a part of a valid UTF-8 text.
+    ///
+    /// For messages ending in the middle of a code point it is equal to message length.
+    pub offset: u64,
+}
+
+/// When [`Utf8Validator`] should report errors.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+pub enum Utf8ValidationMode {
+    /// Report error from the [`Utf8Validator::feed`] call which encountered the invalid byte.
+    #[default]
+    FailFast,
+    /// Only report errors from [`Utf8Validator::finish`], at the end of the message.
+    AtMessageEnd,
+}
+
+/// Incremental UTF-8 validator for content of [`crate::Opcode::Text`] messages.
+///
+/// Feed it the unmasked payload chunks (e.g. ones reported by [`crate::WebsocketFrameEvent::PayloadChunk`]),
+/// across all frames of a message, then call [`Utf8Validator::finish`] when the message ends.
+/// Code points may be split between chunks and frames arbitrarily.
+///
+/// RFC 6455 requires failing the connection with close code 1007 when text message is not a valid UTF-8.
+///
+/// Example:
+///
+/// ```
+/// use websocket_sans_io::{Utf8Validator, Utf8ValidationMode};
+/// let mut v = Utf8Validator::new(Utf8ValidationMode::FailFast);
+/// v.feed(&"Привет".as_bytes()[..3]).unwrap();
+/// v.feed(&"Привет".as_bytes()[3..]).unwrap();
+/// v.finish().unwrap();
+///
+/// v.feed(b"\xC3\x28").unwrap_err();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Utf8Validator {
+    mode: Utf8ValidationMode,
+    /// Number of continuation bytes expected to complete current code point
+    needed: u8,
+    /// Allowed range of the next continuation byte
+    lower: u8,
+    upper: u8,
+    offset: u64,
+    error: Option<Utf8Error>,
+}
+
+impl Utf8Validator {
+    /// Create new instance.
+    #[inline]
+    pub const fn new(mode: Utf8ValidationMode) -> Self {
+        Utf8Validator {
+            mode,
+            needed: 0,
+            lower: 0x80,
+            upper: 0xBF,
+            offset: 0,
+            error: None,
+        }
+    }
+
+    /// Validate next chunk of the message content.
+    pub fn feed(&mut self, mut chunk: &[u8]) -> Result<(), Utf8Error> {
+        if let Some(e) = self.error {
+            return self.report(e);
+        }
+        while !chunk.is_empty() {
+            if self.needed == 0 {
+                // Fast path for (mostly) complete code points.
+                match core::str::from_utf8(chunk) {
+                    Ok(_) => {
+                        self.offset += chunk.len() as u64;
+                        return Ok(());
+                    }
+                    Err(e) => {
+                        let valid = e.valid_up_to();
+                        self.offset += valid as u64;
+                        chunk = &chunk[valid..];
+                    }
+                }
+            }
+            let b = chunk[0];
+            if !self.step(b) {
+                let e = Utf8Error {
+                    offset: self.offset,
+                };
+                self.error = Some(e);
+                return self.report(e);
+            }
+            self.offset += 1;
+            chunk = &chunk[1..];
+        }
+        Ok(())
+    }
+
+    /// Process one byte. Returns `false` if the byte is invalid in this position.
+    fn step(&mut self, b: u8) -> bool {
+        if self.needed > 0 {
+            if b < self.lower || b > self.upper {
+                return false;
+            }
+            self.needed -= 1;
+            self.lower = 0x80;
+            self.upper = 0xBF;
+            return true;
+        }
+        let (needed, lower, upper) = match b {
+            0x00..=0x7F => (0, 0x80, 0xBF),
+            0xC2..=0xDF => (1, 0x80, 0xBF),
+            0xE0 => (2, 0xA0, 0xBF),
+            0xE1..=0xEC | 0xEE..=0xEF => (2, 0x80, 0xBF),
+            0xED => (2, 0x80, 0x9F),
+            0xF0 => (3, 0x90, 0xBF),
+            0xF1..=0xF3 => (3, 0x80, 0xBF),
+            0xF4 => (3, 0x80, 0x8F),
+            _ => return false,
+        };
+        self.needed = needed;
+        self.lower = lower;
+        self.upper = upper;
+        true
+    }
+
+    fn report(&self, e: Utf8Error) -> Result<(), Utf8Error> {
+        match self.mode {
+            Utf8ValidationMode::FailFast => Err(e),
+            Utf8ValidationMode::AtMessageEnd => Ok(()),
+        }
+    }
+
+    /// Signal the end of message and get final validation result.
+    ///
+    /// Resets the validator, so it can be reused for the next message.
+    pub fn finish(&mut self) -> Result<(), Utf8Error> {
+        let mut ret = match self.error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        };
+        if ret.is_ok() && self.needed > 0 {
+            ret = Err(Utf8Error {
+                offset: self.offset,
+            });
+        }
+        *self = Utf8Validator::new(self.mode);
+        ret
+    }
+
+    /// Check if current message is in the middle of a multi-byte code point.
+    #[inline]
+    pub const fn is_incomplete(&self) -> bool {
+        self.needed > 0
+    }
+}
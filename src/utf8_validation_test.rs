@@ -0,0 +1,83 @@
+extern crate std;
+use std::vec::Vec;
+
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+fn validate_chunked(input: &[u8], chunk_size: usize, mode: Utf8ValidationMode) -> Result<(), Utf8Error> {
+    let mut v = Utf8Validator::new(mode);
+    for chunk in input.chunks(chunk_size) {
+        v.feed(chunk)?;
+    }
+    v.finish()
+}
+
+/// Offset of the first byte which makes the prefix impossible to complete to a valid UTF-8
+fn expected(input: &[u8]) -> Result<(), Utf8Error> {
+    if core::str::from_utf8(input).is_ok() {
+        return Ok(());
+    }
+    for p in 0..input.len() {
+        if let Err(e) = core::str::from_utf8(&input[..=p]) {
+            if e.error_len().is_some() {
+                return Err(Utf8Error { offset: p as u64 });
+            }
+        }
+    }
+    Err(Utf8Error { offset: input.len() as u64 })
+}
+
+const SAMPLES: &[&[u8]] = &[
+    b"",
+    b"Hello",
+    "Привет, 世界! 🎉".as_bytes(),
+    b"\xC3\x28",
+    b"abc\xE0\x80\xAF",
+    b"\xED\xA0\x80",
+    b"\xF4\x90\x80\x80",
+    b"\xF0\x9F\x8E",
+    b"ok\xFF",
+    b"\xCE\xBA\xE1\xBD\xB9\xCF\x83\xCE\xBC\xCE\xB5\xED\xA0\x80edited",
+];
+
+#[test]
+fn utf8_any_chunking() {
+    for sample in SAMPLES {
+        let exp = expected(sample);
+        for chunk_size in 1..=sample.len().max(1) {
+            assert_eq!(validate_chunked(sample, chunk_size, Utf8ValidationMode::FailFast), exp, "{sample:?} by {chunk_size}");
+            assert_eq!(validate_chunked(sample, chunk_size, Utf8ValidationMode::AtMessageEnd), exp, "{sample:?} by {chunk_size}");
+        }
+    }
+}
+
+#[test]
+fn utf8_error_offsets() {
+    assert_eq!(validate_chunked(b"\xC3\x28", 1, Utf8ValidationMode::FailFast), Err(Utf8Error { offset: 1 }));
+    assert_eq!(validate_chunked(b"ok\xFF", 1, Utf8ValidationMode::FailFast), Err(Utf8Error { offset: 2 }));
+    assert_eq!(validate_chunked(b"\xF0\x9F\x8E", 2, Utf8ValidationMode::FailFast), Err(Utf8Error { offset: 3 }));
+}
+
+#[test]
+fn utf8_lenient_mode_reports_at_end() {
+    let mut v = Utf8Validator::new(Utf8ValidationMode::AtMessageEnd);
+    assert_eq!(v.feed(b"a\xFFb"), Ok(()));
+    assert_eq!(v.feed(b"more"), Ok(()));
+    assert_eq!(v.finish(), Err(Utf8Error { offset: 1 }));
+    // Validator is reset after `finish`
+    assert_eq!(v.feed("ü".as_bytes()), Ok(()));
+    assert_eq!(v.finish(), Ok(()));
+}
+
+#[test]
+fn utf8_all_two_byte_prefixes() {
+    let mut input = Vec::new();
+    for a in 0..=255u8 {
+        for b in 0..=255u8 {
+            input.clear();
+            input.extend_from_slice(&[a, b, b'x']);
+            assert_eq!(validate_chunked(&input, 1, Utf8ValidationMode::FailFast), expected(&input), "{input:?}");
+        }
+    }
+}
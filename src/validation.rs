@@ -0,0 +1,336 @@
+use crate::{
+    CloseCode, Extension, FrameDecoderError, FrameDecoderStrictness, FrameInfo, Opcode, PayloadLength, Role, Utf8Error, Utf8ValidationMode,
+    Utf8Validator, WebsocketFrameDecoder, WebsocketFrameDecoderAddDataResult, WebsocketFrameEvent,
+};
+
+/// Maximum payload length of a control frame, as per RFC 6455.
+pub const MAX_CONTROL_PAYLOAD_LENGTH: u8 = 125;
+
+/// Resource limits for incoming frames, checked by [`ValidatingFrameDecoder`] as soon as a frame header is parsed.
+///
+/// Default value imposes no limits besides those of RFC 6455.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct DecoderLimits {
+    /// Maximum payload length of a single data frame.
+    pub max_frame_payload: PayloadLength,
+    /// Maximum total payload length of a data message, summed over all its frames.
+    pub max_message_size: u64,
+    /// Maximum number of frames a data message can be fragmented into.
+    pub max_fragments: u32,
+    /// Maximum payload length of a control frame. Values above [`MAX_CONTROL_PAYLOAD_LENGTH`] have no effect.
+    pub max_control_payload: u8,
+}
+
+impl DecoderLimits {
+    /// No limits besides those of RFC 6455.
+    pub const fn unlimited() -> Self {
+        DecoderLimits {
+            max_frame_payload: PayloadLength::MAX,
+            max_message_size: u64::MAX,
+            max_fragments: u32::MAX,
+            max_control_payload: MAX_CONTROL_PAYLOAD_LENGTH,
+        }
+    }
+}
+
+impl Default for DecoderLimits {
+    fn default() -> Self {
+        DecoderLimits::unlimited()
+    }
+}
+
+/// Which of [`DecoderLimits`] was exceeded.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum DecoderLimit {
+    /// [`DecoderLimits::max_frame_payload`]
+    FramePayload,
+    /// [`DecoderLimits::max_message_size`]
+    MessageSize,
+    /// [`DecoderLimits::max_fragments`]
+    Fragments,
+    /// [`DecoderLimits::max_control_payload`]
+    ControlPayload,
+}
+
+/// Problem found by [`ValidatingFrameDecoder`] in incoming WebSocket frames.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum ProtocolViolation {
+    /// Underlying [`WebsocketFrameDecoder`] failed.
+    FrameDecoder(FrameDecoderError),
+    /// Frame has [`FrameInfo::reserved`] bits not claimed by any allowed [`Extension`].
+    ReservedBits,
+    /// Frame uses a reserved opcode (e.g. [`Opcode::ReservedData3`]) not claimed by any allowed [`Extension`].
+    ReservedOpcode,
+    /// [`Opcode::Continuation`] frame arrived when there is no unfinished message.
+    OrphanContinuation,
+    /// A new data frame (e.g. [`Opcode::Text`]) arrived when previous message is not finished yet.
+    UnfinishedMessage,
+    /// Control frame (e.g. [`Opcode::Ping`]) has `fin` flag unset.
+    FragmentedControlFrame,
+    /// Control frame payload is longer than [`MAX_CONTROL_PAYLOAD_LENGTH`].
+    ControlFrameTooLarge,
+    /// Content of a [`Opcode::Text`] message is not a valid UTF-8.
+    InvalidUtf8(Utf8Error),
+    /// Server received a frame without masking.
+    UnmaskedFrame,
+    /// Client received a masked frame.
+    MaskedFrame,
+    /// Frame or message exceeds one of [`DecoderLimits`] set with [`ValidatingFrameDecoder::set_limits`].
+    LimitExceeded(DecoderLimit),
+}
+
+impl ProtocolViolation {
+    /// Status code to be sent in [`Opcode::ConnectionClose`] frame when failing the connection due to this violation.
+    pub const fn close_code(&self) -> CloseCode {
+        match self {
+            ProtocolViolation::FrameDecoder(FrameDecoderError::ExceededFrameSize) => CloseCode::MessageTooBig,
+            ProtocolViolation::InvalidUtf8(_) => CloseCode::InvalidPayload,
+            ProtocolViolation::LimitExceeded(_) => CloseCode::MessageTooBig,
+            _ => CloseCode::ProtocolError,
+        }
+    }
+}
+
+impl From<FrameDecoderError> for ProtocolViolation {
+    fn from(value: FrameDecoderError) -> Self {
+        ProtocolViolation::FrameDecoder(value)
+    }
+}
+
+/// A wrapper around [`WebsocketFrameDecoder`] which checks that incoming frames
+/// form a valid RFC 6455 frame sequence.
+///
+/// It emits the same events as the wrapped decoder and should be used the same way.
+///
+/// Checked things:
+///
+/// * Reserved bits and reserved opcodes, unless claimed by an extension registered with [`ValidatingFrameDecoder::allow_extension`]
+/// * Orphaned [`Opcode::Continuation`] frames and data frames interrupting unfinished messages
+/// * Fragmented or oversized control frames
+/// * UTF-8 validity of [`Opcode::Text`] messages (can be turned off using [`ValidatingFrameDecoder::set_utf8_validation`]),
+///   except those with an extension RSV bit set in the first frame, e.g. compressed ones
+/// * Masking of frames, if [`Role`] is specified
+/// * Resource limits, if set with [`ValidatingFrameDecoder::set_limits`]
+///
+/// After an error is returned, the connection should be failed with [`ProtocolViolation::close_code`].
+#[derive(Clone, Copy, Debug)]
+pub struct ValidatingFrameDecoder {
+    decoder: WebsocketFrameDecoder,
+    current_message: Option<Opcode>,
+    validate_utf8: bool,
+    utf8: Utf8Validator,
+    role: Option<Role>,
+    /// Allowed RSV bits, indexed by opcode.
+    allowed_reserved_bits: [u8; 16],
+    allowed_opcodes: u16,
+    /// Payload of the current message is transformed by an extension, so it is not checked for UTF-8 validity.
+    message_transformed: bool,
+    limits: DecoderLimits,
+    /// Total payload length of the current message, including the frame being received.
+    message_size: u64,
+    /// Number of frames of the current message received so far.
+    message_fragments: u32,
+}
+
+impl Default for ValidatingFrameDecoder {
+    fn default() -> Self {
+        ValidatingFrameDecoder::new()
+    }
+}
+
+impl ValidatingFrameDecoder {
+    /// Create new instance, with UTF-8 validation in [`Utf8ValidationMode::FailFast`] mode.
+    #[inline]
+    pub const fn new() -> Self {
+        ValidatingFrameDecoder {
+            decoder: WebsocketFrameDecoder::new(),
+            current_message: None,
+            validate_utf8: true,
+            utf8: Utf8Validator::new(Utf8ValidationMode::FailFast),
+            role: None,
+            allowed_reserved_bits: [0; 16],
+            allowed_opcodes: 0,
+            message_transformed: false,
+            limits: DecoderLimits::unlimited(),
+            message_size: 0,
+            message_fragments: 0,
+        }
+    }
+
+    /// Create new instance which also checks that incoming frames are masked
+    /// (for [`Role::Server`]) or unmasked (for [`Role::Client`]).
+    #[inline]
+    pub const fn with_role(role: Role) -> Self {
+        let mut d = ValidatingFrameDecoder::new();
+        d.role = Some(role);
+        d
+    }
+
+    /// Set or clear the role used for checking masking of incoming frames.
+    #[inline]
+    pub fn set_role(&mut self, role: Option<Role>) {
+        self.role = role;
+    }
+
+    /// Enable optional wire-format checks in the underlying [`WebsocketFrameDecoder`].
+    ///
+    /// Their errors are reported as [`ProtocolViolation::FrameDecoder`].
+    #[inline]
+    pub fn set_strictness(&mut self, strictness: FrameDecoderStrictness) {
+        self.decoder.set_strictness(strictness);
+    }
+
+    /// Set resource limits for subsequent frames.
+    #[inline]
+    pub fn set_limits(&mut self, limits: DecoderLimits) {
+        self.limits = limits;
+    }
+
+    /// Currently active resource limits.
+    #[inline]
+    pub const fn limits(&self) -> DecoderLimits {
+        self.limits
+    }
+
+    /// Treat RSV bits and reserved opcodes claimed by `extension` as legal.
+    ///
+    /// RSV bits are allowed only in frames accepted by [`Extension::reserved_bits_for_opcode`].
+    /// [`Opcode::Text`] messages whose first frame has any RSV bit set are not checked for UTF-8 validity,
+    /// as their payload is transformed (e.g. compressed). Caller must validate the decoded payload of such messages.
+    ///
+    /// Call it for each negotiated extension (or once for a chain of them).
+    pub fn allow_extension(&mut self, extension: &impl Extension) {
+        for opcode in 0..=0xF {
+            let opcode = Opcode::from_u8(opcode);
+            self.allowed_reserved_bits[opcode as usize] |= extension.reserved_bits_for_opcode(opcode);
+            if extension.claims_opcode(opcode) {
+                self.allowed_opcodes |= 1 << opcode as u8;
+            }
+        }
+    }
+
+    /// Turn UTF-8 validation of [`Opcode::Text`] messages on (`Some`) or off (`None`).
+    ///
+    /// Should be called between messages.
+    pub fn set_utf8_validation(&mut self, mode: Option<Utf8ValidationMode>) {
+        if let Some(mode) = mode {
+            self.utf8 = Utf8Validator::new(mode);
+        }
+        self.validate_utf8 = mode.is_some();
+    }
+
+    /// Add some bytes to the decoder and return events, if any.
+    ///
+    /// Same as [`WebsocketFrameDecoder::add_data`], but checks for protocol violations.
+    pub fn add_data(
+        &mut self,
+        data: &mut [u8],
+    ) -> Result<WebsocketFrameDecoderAddDataResult, ProtocolViolation> {
+        let ret = self.decoder.add_data(data)?;
+        match ret.event {
+            Some(WebsocketFrameEvent::Start { ref frame_info, .. }) => {
+                self.check_frame_start(frame_info)?;
+            }
+            Some(WebsocketFrameEvent::PayloadChunk {
+                original_opcode: Opcode::Text,
+            }) if self.validate_utf8 && !self.message_transformed => {
+                self.utf8
+                    .feed(&data[..ret.consumed_bytes])
+                    .map_err(ProtocolViolation::InvalidUtf8)?;
+            }
+            Some(WebsocketFrameEvent::End {
+                ref frame_info,
+                original_opcode,
+            }) if frame_info.opcode.is_data() && frame_info.fin => {
+                self.current_message = None;
+                if original_opcode == Opcode::Text && self.validate_utf8 && !self.message_transformed {
+                    self.utf8.finish().map_err(ProtocolViolation::InvalidUtf8)?;
+                }
+            }
+            _ => (),
+        }
+        Ok(ret)
+    }
+
+    fn check_frame_start(&mut self, frame_info: &FrameInfo) -> Result<(), ProtocolViolation> {
+        if frame_info.reserved & !self.allowed_reserved_bits[frame_info.opcode as usize] != 0 {
+            return Err(ProtocolViolation::ReservedBits);
+        }
+        if let Some(role) = self.role {
+            match (role.expects_masked_incoming(), frame_info.mask.is_some()) {
+                (true, false) => return Err(ProtocolViolation::UnmaskedFrame),
+                (false, true) => return Err(ProtocolViolation::MaskedFrame),
+                _ => (),
+            }
+        }
+        match frame_info.opcode {
+            Opcode::Continuation => {
+                if self.current_message.is_none() {
+                    return Err(ProtocolViolation::OrphanContinuation);
+                }
+                self.check_data_frame_limits(frame_info)?;
+            }
+            Opcode::Text | Opcode::Binary => self.check_message_start(frame_info)?,
+            Opcode::ConnectionClose | Opcode::Ping | Opcode::Pong => self.check_control_frame(frame_info)?,
+            x if self.allowed_opcodes & (1 << x as u8) == 0 => return Err(ProtocolViolation::ReservedOpcode),
+            x if x.is_data() => self.check_message_start(frame_info)?,
+            _ => self.check_control_frame(frame_info)?,
+        }
+        Ok(())
+    }
+
+    fn check_message_start(&mut self, frame_info: &FrameInfo) -> Result<(), ProtocolViolation> {
+        if self.current_message.is_some() {
+            return Err(ProtocolViolation::UnfinishedMessage);
+        }
+        self.current_message = Some(frame_info.opcode);
+        self.message_transformed = frame_info.reserved != 0;
+        self.message_size = 0;
+        self.message_fragments = 0;
+        self.check_data_frame_limits(frame_info)
+    }
+
+    fn check_data_frame_limits(&mut self, frame_info: &FrameInfo) -> Result<(), ProtocolViolation> {
+        if frame_info.payload_length > self.limits.max_frame_payload {
+            return Err(ProtocolViolation::LimitExceeded(DecoderLimit::FramePayload));
+        }
+        #[allow(clippy::useless_conversion)] // `PayloadLength` is `u16` without `large_frames`
+        let payload_length = u64::from(frame_info.payload_length);
+        self.message_size = self.message_size.saturating_add(payload_length);
+        if self.message_size > self.limits.max_message_size {
+            return Err(ProtocolViolation::LimitExceeded(DecoderLimit::MessageSize));
+        }
+        self.message_fragments = self.message_fragments.saturating_add(1);
+        if self.message_fragments > self.limits.max_fragments {
+            return Err(ProtocolViolation::LimitExceeded(DecoderLimit::Fragments));
+        }
+        Ok(())
+    }
+
+    fn check_control_frame(&self, frame_info: &FrameInfo) -> Result<(), ProtocolViolation> {
+        if !frame_info.fin {
+            return Err(ProtocolViolation::FragmentedControlFrame);
+        }
+        if frame_info.payload_length > MAX_CONTROL_PAYLOAD_LENGTH.into() {
+            return Err(ProtocolViolation::ControlFrameTooLarge);
+        }
+        if frame_info.payload_length > self.limits.max_control_payload.into() {
+            return Err(ProtocolViolation::LimitExceeded(DecoderLimit::ControlPayload));
+        }
+        Ok(())
+    }
+
+    /// Opcode of a data message which is started, but not finished yet.
+    #[inline]
+    pub const fn current_message(&self) -> Option<Opcode> {
+        self.current_message
+    }
+
+    /// There is no incomplete WebSocket frame at this moment and EOF is valid here.
+    ///
+    /// Same as [`WebsocketFrameDecoder::eof_valid`].
+    #[inline]
+    pub fn eof_valid(&self) -> bool {
+        self.decoder.eof_valid()
+    }
+}
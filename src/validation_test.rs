@@ -0,0 +1,107 @@
+extern crate std;
+
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+use crate::test_util::decode_events;
+
+fn validate(input: &[u8], max_chunk_size: usize) -> Result<(), ProtocolViolation> {
+    validate_limited(input, max_chunk_size, DecoderLimits::unlimited())
+}
+
+fn validate_limited(input: &[u8], max_chunk_size: usize, limits: DecoderLimits) -> Result<(), ProtocolViolation> {
+    let mut d = ValidatingFrameDecoder::new();
+    d.set_limits(limits);
+    decode_events(&mut d, input, max_chunk_size).map(drop)
+}
+
+#[test]
+fn validate_good_sequences() {
+    for mcs in [1, 2, 100] {
+        assert_eq!(validate(b"\x81\x05Hello", mcs), Ok(()));
+        assert_eq!(validate(b"\x01\x03Hel\x89\x00\x80\x02lo", mcs), Ok(()));
+        assert_eq!(validate(b"\x01\x02\xD0\x9F\x00\x01\xD1\x80\x80\x02\xD0\xB8", mcs), Ok(()));
+        assert_eq!(validate(b"\x88\x02\x03\xE8", mcs), Ok(()));
+    }
+}
+
+#[test]
+fn validate_violations() {
+    for mcs in [1, 3, 100] {
+        assert_eq!(validate(b"\x91\x00", mcs), Err(ProtocolViolation::ReservedBits));
+        assert_eq!(validate(b"\x83\x00", mcs), Err(ProtocolViolation::ReservedOpcode));
+        assert_eq!(validate(b"\x8B\x00", mcs), Err(ProtocolViolation::ReservedOpcode));
+        assert_eq!(validate(b"\x80\x00", mcs), Err(ProtocolViolation::OrphanContinuation));
+        assert_eq!(validate(b"\x01\x00\x82\x00", mcs), Err(ProtocolViolation::UnfinishedMessage));
+        assert_eq!(validate(b"\x09\x00", mcs), Err(ProtocolViolation::FragmentedControlFrame));
+        assert_eq!(validate(b"\x89\x7E\x00\x7E", mcs), Err(ProtocolViolation::ControlFrameTooLarge));
+        assert_eq!(validate(b"\x81\x02\xC3\x28", mcs), Err(ProtocolViolation::InvalidUtf8(Utf8Error { offset: 1 })));
+        assert_eq!(validate(b"\x01\x01\xC3\x80\x00", mcs), Err(ProtocolViolation::InvalidUtf8(Utf8Error { offset: 1 })));
+    }
+}
+
+#[test]
+fn validate_close_codes() {
+    assert_eq!(ProtocolViolation::OrphanContinuation.close_code(), CloseCode::ProtocolError);
+    assert_eq!(ProtocolViolation::InvalidUtf8(Utf8Error { offset: 0 }).close_code(), CloseCode::InvalidPayload);
+    assert_eq!(ProtocolViolation::LimitExceeded(DecoderLimit::Fragments).close_code(), CloseCode::MessageTooBig);
+    assert_eq!(ProtocolViolation::FrameDecoder(FrameDecoderError::ExceededFrameSize).close_code(), CloseCode::MessageTooBig);
+    assert_eq!(ProtocolViolation::FrameDecoder(FrameDecoderError::NonMinimalLength).close_code(), CloseCode::ProtocolError);
+}
+
+#[test]
+fn validate_limits() {
+    let limits = DecoderLimits {
+        max_frame_payload: 4,
+        max_message_size: 6,
+        max_fragments: 2,
+        max_control_payload: 2,
+    };
+    for mcs in [1, 3, 100] {
+        assert_eq!(validate_limited(b"\x82\x04abcd", mcs, limits), Ok(()));
+        assert_eq!(validate_limited(b"\x02\x03abc\x89\x02pp\x80\x03abc", mcs, limits), Ok(()));
+        assert_eq!(
+            validate_limited(b"\x82\x05abcde", mcs, limits),
+            Err(ProtocolViolation::LimitExceeded(DecoderLimit::FramePayload)),
+        );
+        assert_eq!(
+            validate_limited(b"\x02\x04abcd\x80\x03abc", mcs, limits),
+            Err(ProtocolViolation::LimitExceeded(DecoderLimit::MessageSize)),
+        );
+        assert_eq!(
+            validate_limited(b"\x02\x01a\x00\x01b\x80\x01c", mcs, limits),
+            Err(ProtocolViolation::LimitExceeded(DecoderLimit::Fragments)),
+        );
+        assert_eq!(
+            validate_limited(b"\x89\x03ppp", mcs, limits),
+            Err(ProtocolViolation::LimitExceeded(DecoderLimit::ControlPayload)),
+        );
+        assert_eq!(validate_limited(b"\x89\x7E\x00\x7E", mcs, limits), Err(ProtocolViolation::ControlFrameTooLarge));
+        // Limits are checked before the payload arrives.
+        #[cfg(feature = "large_frames")]
+        assert_eq!(
+            validate_limited(b"\x82\x7F\x00\x00\x00\x00\x00\x01\x00\x00", mcs, limits),
+            Err(ProtocolViolation::LimitExceeded(DecoderLimit::FramePayload)),
+        );
+    }
+}
+
+#[test]
+fn validate_utf8_can_be_disabled() {
+    let mut d = ValidatingFrameDecoder::new();
+    d.set_utf8_validation(None);
+    decode_events(&mut d, b"\x81\x01\xFF", 1).unwrap();
+}
+
+#[test]
+fn validate_role_masking() {
+    let mut input = *b"\x81\x00\x81\x80\x01\x02\x03\x04";
+    let mut d = ValidatingFrameDecoder::with_role(Role::Client);
+    assert_eq!(d.add_data(&mut input[..2]).map(|x|x.event.is_some()), Ok(true));
+    assert_eq!(d.add_data(&mut []).map(|x|x.event.is_some()), Ok(true));
+    assert_eq!(d.add_data(&mut input[2..]).err(), Some(ProtocolViolation::MaskedFrame));
+
+    let mut d = ValidatingFrameDecoder::with_role(Role::Server);
+    assert_eq!(d.add_data(&mut input[..2]).err(), Some(ProtocolViolation::UnmaskedFrame));
+}